@@ -0,0 +1,142 @@
+use std::any::TypeId;
+use std::fmt;
+
+/// The data a system or query borrows from the world.
+#[derive(Debug, Default, Clone)]
+pub struct Access {
+    reads: Vec<AccessedType>,
+    writes: Vec<AccessedType>,
+
+    /// Whether the whole world is borrowed exclusively.
+    world: bool,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct AccessedType {
+    storage: TypeId,
+    name: &'static str,
+}
+
+impl Access {
+    /// Access to the whole world, which conflicts with everything.
+    pub fn world() -> Self {
+        Self {
+            world: true,
+            ..Self::default()
+        }
+    }
+
+    /// Add shared access to a storage, named `name` in errors.
+    pub fn read<S: 'static>(&mut self, name: &'static str) -> Result<(), AccessConflict> {
+        let storage = TypeId::of::<S>();
+
+        if self.world || self.writes.iter().any(|ty| ty.storage == storage) {
+            return Err(AccessConflict { type_name: name });
+        }
+
+        if !self.reads.iter().any(|ty| ty.storage == storage) {
+            self.reads.push(AccessedType { storage, name });
+        }
+        Ok(())
+    }
+
+    /// Add exclusive access to a storage, named `name` in errors.
+    pub fn write<S: 'static>(&mut self, name: &'static str) -> Result<(), AccessConflict> {
+        let storage = TypeId::of::<S>();
+
+        if self.world
+            || self.reads.iter().any(|ty| ty.storage == storage)
+            || self.writes.iter().any(|ty| ty.storage == storage)
+        {
+            return Err(AccessConflict { type_name: name });
+        }
+
+        self.writes.push(AccessedType { storage, name });
+        Ok(())
+    }
+
+    /// Add everything accessed by `other`, without checking for conflicts.
+    ///
+    /// This is for combining things that never borrow at the same time, such
+    /// as piped systems.
+    pub fn extend(&mut self, other: &Access) {
+        self.world |= other.world;
+
+        for ty in &other.reads {
+            if !self.reads.iter().any(|read| read.storage == ty.storage) {
+                self.reads.push(*ty);
+            }
+        }
+
+        for ty in &other.writes {
+            if !self.writes.iter().any(|write| write.storage == ty.storage) {
+                self.writes.push(*ty);
+            }
+        }
+    }
+
+    /// Whether the whole world is borrowed exclusively.
+    #[inline]
+    pub fn is_world(&self) -> bool {
+        self.world
+    }
+
+    /// The names of the types borrowed immutably.
+    pub fn reads(&self) -> impl Iterator<Item = &'static str> + '_ {
+        self.reads.iter().map(|ty| ty.name)
+    }
+
+    /// The names of the types borrowed mutably.
+    pub fn writes(&self) -> impl Iterator<Item = &'static str> + '_ {
+        self.writes.iter().map(|ty| ty.name)
+    }
+
+    /// The names of the types that can't be borrowed by `self` and `other` at
+    /// the same time.
+    pub fn conflicts(&self, other: &Access) -> Vec<&'static str> {
+        if self.world || other.world {
+            return vec!["World"];
+        }
+
+        let mut conflicts = vec![];
+
+        for write in &self.writes {
+            if other
+                .reads
+                .iter()
+                .chain(&other.writes)
+                .any(|ty| ty.storage == write.storage)
+            {
+                conflicts.push(write.name);
+            }
+        }
+
+        for write in &other.writes {
+            if self.reads.iter().any(|ty| ty.storage == write.storage) {
+                conflicts.push(write.name);
+            }
+        }
+
+        conflicts
+    }
+
+    /// Whether `self` and `other` can be borrowed at the same time.
+    #[inline]
+    pub fn is_compatible(&self, other: &Access) -> bool {
+        self.conflicts(other).is_empty()
+    }
+}
+
+/// A type that is borrowed mutably alongside another borrow of itself.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AccessConflict {
+    pub type_name: &'static str,
+}
+
+impl fmt::Display for AccessConflict {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "conflicting access to {}", self.type_name)
+    }
+}
+
+impl std::error::Error for AccessConflict {}
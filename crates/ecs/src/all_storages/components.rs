@@ -1,4 +1,4 @@
-use std::cell::{Ref, RefMut};
+use std::any::TypeId;
 
 use super::erased::*;
 use crate::prelude::*;
@@ -6,6 +6,10 @@ use crate::prelude::*;
 impl<C: Component> ErasableStorage for ComponentStorage<C> {
     type ErasedStorage = ErasedStorageWithEntities;
 
+    fn type_name() -> &'static str {
+        std::any::type_name::<C>()
+    }
+
     fn erase(self) -> Self::ErasedStorage {
         ErasedStorageWithEntities::new(self)
     }
@@ -20,42 +24,84 @@ impl<C: Component> ErasableStorage for ComponentStorage<C> {
 }
 
 #[derive(Default)]
-pub struct AllComponentStorages(ErasedStorages<ErasedStorageWithEntities>);
+pub struct AllComponentStorages {
+    storages: ErasedStorages<ErasedStorageWithEntities>,
+
+    /// Indexed by component id, which is the storage's index.
+    infos: Vec<ComponentInfo>,
+}
 
 impl AllComponentStorages {
     #[inline]
     pub fn lookup<C: Component>(&self) -> BorrowResult<StorageIdx<ComponentStorage<C>>> {
-        self.0.lookup()
+        self.storages.lookup()
+    }
+
+    /// Look up a storage, creating it if it doesn't exist.
+    ///
+    /// Entities have to be told about new component types, so this should only
+    /// be used through [`AllStorages::register_component`].
+    pub(super) fn lookup_or_insert<C: Component>(&mut self) -> StorageIdx<ComponentStorage<C>> {
+        let idx = self.storages.lookup_or_insert::<ComponentStorage<C>>();
+
+        if idx.index() == self.infos.len() {
+            let id = ComponentId(idx.index());
+            let storage = self.storages.get_mut(idx);
+            storage.set_id(id);
+            C::hooks(storage.hooks_mut());
+            self.infos.push(ComponentInfo {
+                id,
+                type_id: TypeId::of::<C>(),
+                name: std::any::type_name::<C>(),
+            });
+        }
+
+        idx
     }
 
     #[inline]
-    pub fn lookup_or_insert<C: Component>(&mut self) -> StorageIdx<ComponentStorage<C>> {
-        self.0.lookup_or_insert()
+    pub fn info(&self, id: ComponentId) -> Option<&ComponentInfo> {
+        self.infos.get(id.index())
     }
 
     #[inline]
+    pub fn infos(&self) -> &[ComponentInfo] {
+        &self.infos
+    }
+
+    #[inline]
+    #[cfg_attr(debug_assertions, track_caller)]
     pub fn borrow_ref<C: Component>(
         &self,
         idx: StorageIdx<ComponentStorage<C>>,
-    ) -> BorrowResult<Ref<ComponentStorage<C>>> {
-        self.0.borrow_ref(idx)
+    ) -> BorrowResult<Ref<'_, ComponentStorage<C>>> {
+        self.storages.borrow_ref(idx)
     }
 
     #[inline]
+    #[cfg_attr(debug_assertions, track_caller)]
     pub fn borrow_mut<C: Component>(
         &self,
         idx: StorageIdx<ComponentStorage<C>>,
-    ) -> BorrowResult<RefMut<ComponentStorage<C>>> {
-        self.0.borrow_mut(idx)
+    ) -> BorrowResult<RefMut<'_, ComponentStorage<C>>> {
+        self.storages.borrow_mut(idx)
+    }
+
+    #[inline]
+    pub fn get_mut<C: Component>(
+        &mut self,
+        idx: StorageIdx<ComponentStorage<C>>,
+    ) -> &mut ComponentStorage<C> {
+        self.storages.get_mut(idx)
     }
 
     #[inline]
-    pub fn iter_refs(&self) -> ErasedStorageIter<ErasedStorageWithEntities> {
-        self.0.iter_refs()
+    pub fn iter_refs(&self) -> ErasedStorageIter<'_, ErasedStorageWithEntities> {
+        self.storages.iter_refs()
     }
 
     #[inline]
-    pub fn iter_muts(&mut self) -> ErasedStorageIterMut<ErasedStorageWithEntities> {
-        self.0.iter_muts()
+    pub fn iter_muts(&mut self) -> ErasedStorageIterMut<'_, ErasedStorageWithEntities> {
+        self.storages.iter_muts()
     }
 }
@@ -1,16 +1,24 @@
 use std::any::{Any, TypeId};
-use std::cell::{Ref, RefCell, RefMut};
 use std::collections::hash_map::Entry;
 use std::collections::HashMap;
 use std::marker::PhantomData;
 use std::slice::Iter;
 
-use crate::prelude::{BorrowError, BorrowResult};
-use crate::storage::StorageWithEntities;
+use parking_lot::{RwLock, RwLockReadGuard, RwLockWriteGuard};
+
+use super::AllStorages;
+use crate::prelude::{
+    BorrowError, BorrowKind, BorrowResult, Commands, EntityId, EntityMap, LiveEntity, MemoryUsage,
+    Ref, RefMut,
+};
+use crate::storage::{Borrowers, StorageWithEntities};
 
 pub trait ErasableStorage: Any + Sized {
     type ErasedStorage;
 
+    /// The name of the component or unique in the storage, for errors.
+    fn type_name() -> &'static str;
+
     fn erase(self) -> Self::ErasedStorage;
 
     fn downcast_ref(erased: &Self::ErasedStorage) -> Option<&Self>;
@@ -19,7 +27,82 @@ pub trait ErasableStorage: Any + Sized {
 
 pub struct StorageIdx<S: ErasableStorage> {
     idx: usize,
-    phantom_data: PhantomData<S>,
+    phantom_data: PhantomData<fn() -> S>,
+}
+
+impl<S: ErasableStorage> Clone for StorageIdx<S> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<S: ErasableStorage> Copy for StorageIdx<S> {}
+
+impl<S: ErasableStorage> StorageIdx<S> {
+    /// The position of the storage, in the order storages were created.
+    #[inline]
+    pub fn index(self) -> usize {
+        self.idx
+    }
+}
+
+/// A storage, along with what's needed to explain why it can't be borrowed.
+///
+/// Storages are behind a lock that is never waited on, so that a world can
+/// be shared between threads while borrows that would conflict fail just as
+/// they would with a `RefCell`.
+struct StorageCell<ErasedStorage> {
+    storage: RwLock<ErasedStorage>,
+    type_name: &'static str,
+    borrowers: Borrowers,
+}
+
+impl<ErasedStorage> StorageCell<ErasedStorage> {
+    fn new(storage: ErasedStorage, type_name: &'static str) -> Self {
+        Self {
+            storage: RwLock::new(storage),
+            type_name,
+            borrowers: Borrowers::default(),
+        }
+    }
+
+    #[cfg_attr(debug_assertions, track_caller)]
+    fn borrow(&self) -> BorrowResult<Ref<'_, ErasedStorage>> {
+        match self.storage.try_read() {
+            Some(storage) => Ok(Ref::new(
+                RwLockReadGuard::map(storage, |storage| storage),
+                self.borrowers.record(),
+            )),
+            None => Err(self.invalid_borrow(BorrowKind::Shared, BorrowKind::Exclusive)),
+        }
+    }
+
+    #[cfg_attr(debug_assertions, track_caller)]
+    fn borrow_mut(&self) -> BorrowResult<RefMut<'_, ErasedStorage>> {
+        match self.storage.try_write() {
+            Some(storage) => Ok(RefMut::new(
+                RwLockWriteGuard::map(storage, |storage| storage),
+                self.borrowers.record(),
+            )),
+            None => {
+                // Only a mutable borrow stops an immutable borrow.
+                let held = match self.storage.try_read() {
+                    Some(_) => BorrowKind::Shared,
+                    None => BorrowKind::Exclusive,
+                };
+                Err(self.invalid_borrow(BorrowKind::Exclusive, held))
+            }
+        }
+    }
+
+    fn invalid_borrow(&self, requested: BorrowKind, held: BorrowKind) -> BorrowError {
+        BorrowError::InvalidBorrow {
+            type_name: self.type_name,
+            requested,
+            held,
+            borrower: self.borrowers.last(),
+        }
+    }
 }
 
 // By storing the storages inside a `Vec`, storage insertion doesn't
@@ -27,7 +110,7 @@ pub struct StorageIdx<S: ErasableStorage> {
 // once.
 pub(crate) struct ErasedStorages<ErasedStorage> {
     lookup: HashMap<TypeId, usize>,
-    storages: Vec<RefCell<ErasedStorage>>,
+    storages: Vec<StorageCell<ErasedStorage>>,
 }
 
 impl<ErasedStorage> Default for ErasedStorages<ErasedStorage> {
@@ -40,26 +123,6 @@ impl<ErasedStorage> Default for ErasedStorages<ErasedStorage> {
 }
 
 impl<ErasedStorage> ErasedStorages<ErasedStorage> {
-    /// Panics if the new storage capacity exceeds `isize::MAX` bytes.
-    pub fn insert<S: ErasableStorage<ErasedStorage = ErasedStorage>>(
-        &mut self,
-        storage: S,
-    ) -> Option<usize> {
-        let type_id = TypeId::of::<S>();
-        match self.lookup.entry(type_id) {
-            Entry::Vacant(vacant) => {
-                let idx = self.storages.len();
-                let storage = RefCell::new(storage.erase());
-
-                self.storages.push(storage);
-                vacant.insert(idx);
-
-                Some(idx)
-            }
-            Entry::Occupied(_) => None,
-        }
-    }
-
     pub fn lookup<S: ErasableStorage<ErasedStorage = ErasedStorage>>(
         &self,
     ) -> BorrowResult<StorageIdx<S>> {
@@ -68,7 +131,9 @@ impl<ErasedStorage> ErasedStorages<ErasedStorage> {
             .lookup
             .get(&type_id)
             .copied()
-            .ok_or(BorrowError::StorageNotFound)?;
+            .ok_or(BorrowError::StorageNotFound {
+                type_name: S::type_name(),
+            })?;
         Ok(StorageIdx {
             idx,
             phantom_data: PhantomData,
@@ -77,12 +142,21 @@ impl<ErasedStorage> ErasedStorages<ErasedStorage> {
 
     pub fn lookup_or_insert<S: ErasableStorage<ErasedStorage = ErasedStorage> + Default>(
         &mut self,
+    ) -> StorageIdx<S> {
+        self.lookup_or_insert_named(S::type_name())
+    }
+
+    /// Like [`ErasedStorages::lookup_or_insert`], but naming the storage's
+    /// contents in errors with `type_name` if the storage is created.
+    pub fn lookup_or_insert_named<S: ErasableStorage<ErasedStorage = ErasedStorage> + Default>(
+        &mut self,
+        type_name: &'static str,
     ) -> StorageIdx<S> {
         let type_id = TypeId::of::<S>();
         let idx = match self.lookup.entry(type_id) {
             Entry::Vacant(vacant) => {
                 let idx = self.storages.len();
-                let storage = RefCell::new(S::default().erase());
+                let storage = StorageCell::new(S::default().erase(), type_name);
 
                 self.storages.push(storage);
                 vacant.insert(idx);
@@ -97,13 +171,12 @@ impl<ErasedStorage> ErasedStorages<ErasedStorage> {
         }
     }
 
+    #[cfg_attr(debug_assertions, track_caller)]
     pub fn borrow_ref<S: ErasableStorage<ErasedStorage = ErasedStorage>>(
         &self,
         idx: StorageIdx<S>,
-    ) -> BorrowResult<Ref<S>> {
-        let erased_storage_ref = self.storages[idx.idx]
-            .try_borrow()
-            .map_err(|_| BorrowError::InvalidBorrow)?;
+    ) -> BorrowResult<Ref<'_, S>> {
+        let erased_storage_ref = self.storages[idx.idx].borrow()?;
 
         let storage = Ref::map(erased_storage_ref, |erased| {
             S::downcast_ref(erased).unwrap()
@@ -112,13 +185,12 @@ impl<ErasedStorage> ErasedStorages<ErasedStorage> {
         Ok(storage)
     }
 
+    #[cfg_attr(debug_assertions, track_caller)]
     pub fn borrow_mut<S: ErasableStorage<ErasedStorage = ErasedStorage>>(
         &self,
         idx: StorageIdx<S>,
-    ) -> BorrowResult<RefMut<S>> {
-        let erased_storage_mut = self.storages[idx.idx]
-            .try_borrow_mut()
-            .map_err(|_| BorrowError::InvalidBorrow)?;
+    ) -> BorrowResult<RefMut<'_, S>> {
+        let erased_storage_mut = self.storages[idx.idx].borrow_mut()?;
 
         let storage = RefMut::map(erased_storage_mut, |erased| {
             S::downcast_mut(erased).unwrap()
@@ -127,40 +199,40 @@ impl<ErasedStorage> ErasedStorages<ErasedStorage> {
         Ok(storage)
     }
 
-    pub fn iter_refs(&self) -> ErasedStorageIter<ErasedStorage> {
+    /// Get a storage without borrow checking, since we have exclusive access.
+    pub fn get_mut<S: ErasableStorage<ErasedStorage = ErasedStorage>>(
+        &mut self,
+        idx: StorageIdx<S>,
+    ) -> &mut S {
+        S::downcast_mut(self.storages[idx.idx].storage.get_mut()).unwrap()
+    }
+
+    pub fn iter_refs(&self) -> ErasedStorageIter<'_, ErasedStorage> {
         ErasedStorageIter(self.storages.iter())
     }
 
-    pub fn iter_muts(&self) -> ErasedStorageIterMut<ErasedStorage> {
+    pub fn iter_muts(&self) -> ErasedStorageIterMut<'_, ErasedStorage> {
         ErasedStorageIterMut(self.storages.iter())
     }
 }
 
-pub struct ErasedStorageIter<'a, ErasedStorage>(Iter<'a, RefCell<ErasedStorage>>);
+pub struct ErasedStorageIter<'a, ErasedStorage>(Iter<'a, StorageCell<ErasedStorage>>);
 
 impl<'a, S> Iterator for ErasedStorageIter<'a, S> {
     type Item = BorrowResult<Ref<'a, S>>;
 
     fn next(&mut self) -> Option<Self::Item> {
-        self.0.next().map(|erased_storage| {
-            erased_storage
-                .try_borrow()
-                .map_err(|_| BorrowError::InvalidBorrow)
-        })
+        self.0.next().map(StorageCell::borrow)
     }
 }
 
-pub struct ErasedStorageIterMut<'a, ErasedStorage>(Iter<'a, RefCell<ErasedStorage>>);
+pub struct ErasedStorageIterMut<'a, ErasedStorage>(Iter<'a, StorageCell<ErasedStorage>>);
 
 impl<'a, S> Iterator for ErasedStorageIterMut<'a, S> {
     type Item = BorrowResult<RefMut<'a, S>>;
 
     fn next(&mut self) -> Option<Self::Item> {
-        self.0.next().map(|erased_storage| {
-            erased_storage
-                .try_borrow_mut()
-                .map_err(|_| BorrowError::InvalidBorrow)
-        })
+        self.0.next().map(StorageCell::borrow_mut)
     }
 }
 
@@ -205,12 +277,12 @@ impl ErasedStorageWithEntities {
 
     #[inline]
     pub fn downcast_ref<S: Any>(&self) -> Option<&S> {
-        self.as_any().downcast_ref()
+        self.0.as_any().downcast_ref()
     }
 
     #[inline]
     pub fn downcast_mut<S: Any>(&mut self) -> Option<&mut S> {
-        self.as_any_mut().downcast_mut()
+        self.0.as_any_mut().downcast_mut()
     }
 
     #[inline]
@@ -225,7 +297,30 @@ impl ErasedStorageWithEntities {
 }
 
 impl StorageWithEntities for ErasedStorageWithEntities {
-    fn remove_entity(&mut self, entity: &crate::prelude::LiveEntity) {
+    fn remove_entity(&mut self, entity: &LiveEntity) {
         self.as_storage_mut().remove_entity(entity);
     }
+
+    fn transfer_entity(
+        &mut self,
+        entity: &LiveEntity,
+        dest: &mut AllStorages,
+        dest_entity: EntityId,
+        entity_map: &EntityMap,
+    ) {
+        self.as_storage_mut()
+            .transfer_entity(entity, dest, dest_entity, entity_map);
+    }
+
+    fn take_commands(&mut self, commands: &mut Commands) {
+        self.as_storage_mut().take_commands(commands);
+    }
+
+    fn shrink_to_fit(&mut self) {
+        self.as_storage_mut().shrink_to_fit();
+    }
+
+    fn memory_usage(&self) -> MemoryUsage {
+        self.as_storage().memory_usage()
+    }
 }
@@ -3,43 +3,180 @@ use crate::storage::StorageWithEntities;
 
 pub mod components;
 pub mod erased;
+pub mod non_send;
 pub mod uniques;
 
-pub use erased::{ErasedStorageIter, ErasedStorageIterMut};
+pub use erased::{ErasedStorageIter, ErasedStorageIterMut, StorageIdx};
 
 pub(crate) use components::AllComponentStorages;
+pub(crate) use non_send::AllNonSendStorages;
 pub(crate) use uniques::AllUniqueStorages;
 
-#[derive(Default)]
 pub struct AllStorages {
     pub(crate) entities: EntityStorage,
     pub(crate) components: AllComponentStorages,
     pub(crate) uniques: AllUniqueStorages,
+    pub(crate) non_send: AllNonSendStorages,
+}
+
+impl Default for AllStorages {
+    fn default() -> Self {
+        let mut all_storages = Self {
+            entities: EntityStorage::default(),
+            components: AllComponentStorages::default(),
+            uniques: AllUniqueStorages::default(),
+            non_send: AllNonSendStorages::default(),
+        };
+
+        let idx = all_storages.register_component::<Disabled>();
+        debug_assert_eq!(ComponentId(idx.index()), Disabled::ID);
+
+        all_storages
+    }
 }
 
 impl AllStorages {
+    /// Look up a component storage, creating it if it doesn't exist.
+    pub fn register_component<C: Component>(&mut self) -> StorageIdx<ComponentStorage<C>> {
+        let idx = self.components.lookup_or_insert::<C>();
+        self.entities.register_component(ComponentId(idx.index()));
+        idx
+    }
+
+    /// Check if an entity has a component, without borrowing its storage.
+    ///
+    /// Panics if the entity is dead.
+    pub(crate) fn contains_component<C: Component>(&self, entity: EntityId) -> bool {
+        let Ok(idx) = self.components.lookup::<C>() else {
+            return false;
+        };
+
+        let entity = self.entities.entity_to_alive(entity);
+        entity.has_component(ComponentId(idx.index()))
+    }
+
+    /// The component types an entity has.
+    ///
+    /// Panics if the entity is dead.
+    pub(crate) fn components_of(
+        &self,
+        entity: EntityId,
+    ) -> impl Iterator<Item = &ComponentInfo> + '_ {
+        let entity = self.entities.entity_to_alive(entity);
+        self.entities
+            .components(&entity)
+            .map(|id| self.components.info(id).unwrap())
+    }
+
     #[inline]
-    pub fn spawn(&mut self) -> EntityMut {
+    pub fn spawn(&mut self) -> EntityMut<'_> {
         let entity = self.entities.spawn();
         EntityMut::new(entity, self)
     }
 
     /// Despawn an entity.
     ///
+    /// Returns an error before removing any components if the entity ran out
+    /// of versions under [`GenerationOverflow::Error`].
+    ///
     /// Panics if the entity is dead or any storage is borrowed.
-    pub(crate) fn despawn_entity(&mut self, entity: EntityId) {
+    pub(crate) fn despawn_entity(
+        &mut self,
+        entity: EntityId,
+    ) -> Result<(), GenerationOverflowError> {
         let live_entity = self.entities.entity_to_alive(entity);
+        self.entities.check_despawn(entity)?;
 
         for storage in self.components.iter_muts() {
             let mut storage = storage.expect("couldn't borrow storage");
             storage.remove_entity(&live_entity);
         }
 
-        self.entities.despawn(entity);
+        self.entities.despawn(entity)
+    }
+
+    /// Despawn every entity, keeping registered component types and hooks.
+    ///
+    /// Returns an error without despawning anything if any entity can't be
+    /// despawned.
+    ///
+    /// Panics if any storage is borrowed.
+    pub(crate) fn clear(&mut self) -> Result<(), GenerationOverflowError> {
+        let entities = self.entities.alive();
+        for &entity in &entities {
+            self.entities.check_despawn(entity)?;
+        }
+
+        for entity in entities {
+            self.despawn_entity(entity)?;
+        }
+        Ok(())
+    }
+
+    /// Release unused capacity in every storage.
+    ///
+    /// Panics if any storage is borrowed.
+    pub(crate) fn shrink_to_fit(&mut self) {
+        for storage in self.components.iter_muts() {
+            storage.expect("couldn't borrow storage").shrink_to_fit();
+        }
+        self.entities.shrink_to_fit();
+    }
+
+    /// Panics if any storage is borrowed mutably.
+    pub(crate) fn memory_usage(&self) -> Vec<MemoryUsage> {
+        let components = self
+            .components
+            .iter_refs()
+            .map(|storage| storage.expect("couldn't borrow storage").memory_usage());
+
+        std::iter::once(self.entities.memory_usage())
+            .chain(components)
+            .collect()
+    }
+
+    /// Move entities and their components into another set of storages.
+    ///
+    /// The entities must already have been spawned in `dest` according to
+    /// `entity_map`, and are despawned from these storages, so they must
+    /// have been checked with [`EntityStorage::check_despawn`].
+    ///
+    /// Panics if any entity is dead or any storage is borrowed.
+    pub(crate) fn transfer_entities(&mut self, dest: &mut AllStorages, entity_map: &EntityMap) {
+        for storage in self.components.iter_muts() {
+            let mut storage = storage.expect("couldn't borrow storage");
+
+            for (from, to) in entity_map.iter() {
+                let from = self.entities.entity_to_alive(from);
+                storage.transfer_entity(&from, dest, to, entity_map);
+            }
+        }
+
+        for (from, _) in entity_map.iter() {
+            self.entities
+                .despawn(from)
+                .expect("transferred entities are checked before being moved");
+        }
+    }
+
+    /// Collect the commands queued by all storages.
+    ///
+    /// Panics if any storage is borrowed.
+    pub(crate) fn take_commands(&mut self) -> Commands {
+        let mut commands = Commands::default();
+
+        for storage in self.components.iter_muts() {
+            let mut storage = storage.expect("couldn't borrow storage");
+            storage.take_commands(&mut commands);
+        }
+
+        commands
     }
 
     #[inline]
-    pub fn entity(&mut self, entity: EntityId) -> EntityMut {
+    pub fn entity(&mut self, entity: EntityId) -> EntityMut<'_> {
+        self.entities.flush();
+
         if !self.entities.is_alive(entity) {
             panic!("entity {entity:?} is dead");
         }
@@ -0,0 +1,149 @@
+use std::any::Any;
+use std::thread::ThreadId;
+
+use send_wrapper::SendWrapper;
+
+use super::erased::StorageIdx;
+use super::AllUniqueStorages;
+use crate::prelude::*;
+use crate::storage::unique::UniqueStorage;
+
+/// A non-send unique, which can only be accessed on the thread that
+/// inserted it.
+pub(crate) type NonSendStorage<T> = UniqueStorage<SendWrapper<T>>;
+
+/// The non-send uniques of a world.
+///
+/// These belong to the thread the world was created on. Borrowing them on
+/// any other thread fails with [`BorrowError::WrongThread`], and modifying
+/// them or dropping the world on another thread panics.
+pub struct AllNonSendStorages {
+    owner: ThreadId,
+    uniques: AllUniqueStorages,
+
+    /// How many non-send uniques exist, since an empty storage can be
+    /// dropped on any thread.
+    len: usize,
+}
+
+impl Default for AllNonSendStorages {
+    fn default() -> Self {
+        Self {
+            owner: std::thread::current().id(),
+            uniques: AllUniqueStorages::default(),
+            len: 0,
+        }
+    }
+}
+
+impl Drop for AllNonSendStorages {
+    fn drop(&mut self) {
+        if self.len == 0 || self.is_owner() {
+            return;
+        }
+
+        // The uniques can't be dropped here, so they are leaked rather than
+        // panicking again while unwinding.
+        std::mem::forget(std::mem::take(&mut self.uniques));
+        if !std::thread::panicking() {
+            panic!("a world with non-send uniques was dropped on another thread");
+        }
+    }
+}
+
+impl AllNonSendStorages {
+    #[inline]
+    fn is_owner(&self) -> bool {
+        self.owner == std::thread::current().id()
+    }
+
+    /// Check that non-send uniques can be accessed on this thread.
+    #[inline]
+    pub fn check_thread<T: Any>(&self) -> BorrowResult<()> {
+        if self.is_owner() {
+            Ok(())
+        } else {
+            Err(BorrowError::WrongThread {
+                type_name: std::any::type_name::<T>(),
+            })
+        }
+    }
+
+    #[track_caller]
+    fn assert_thread<T: Any>(&self) {
+        if let Err(err) = self.check_thread::<T>() {
+            panic!("{err}");
+        }
+    }
+
+    /// Insert a non-send unique if it doesn't already exist.
+    ///
+    /// Panics if called on another thread than the world's.
+    #[track_caller]
+    pub fn insert<T: Any>(&mut self, unique: T) -> Option<()> {
+        self.assert_thread::<T>();
+        self.lookup_or_insert::<T>();
+        let inserted = self.uniques.insert(SendWrapper::new(unique));
+        self.len += inserted.is_some() as usize;
+        inserted
+    }
+
+    /// Insert a non-send unique, returning the previous value if there was one.
+    ///
+    /// Panics if called on another thread than the world's.
+    #[track_caller]
+    pub fn replace<T: Any>(&mut self, unique: T) -> Option<T> {
+        self.assert_thread::<T>();
+        self.lookup_or_insert::<T>();
+        let prev = self.uniques.replace(SendWrapper::new(unique));
+        self.len += prev.is_none() as usize;
+        prev.map(SendWrapper::take)
+    }
+
+    /// Panics if called on another thread than the world's.
+    #[track_caller]
+    pub fn remove<T: Any>(&mut self) -> Option<T> {
+        self.assert_thread::<T>();
+        let prev = self.uniques.remove::<SendWrapper<T>>();
+        self.len -= prev.is_some() as usize;
+        prev.map(SendWrapper::take)
+    }
+
+    /// Panics if called on another thread than the world's.
+    #[track_caller]
+    pub fn get_or_insert_with<T: Any>(&mut self, f: impl FnOnce() -> T) -> &mut T {
+        self.assert_thread::<T>();
+        self.lookup_or_insert::<T>();
+        self.uniques.get_or_insert_with(|| {
+            self.len += 1;
+            SendWrapper::new(f())
+        })
+    }
+
+    #[inline]
+    pub fn lookup_or_insert<T: Any>(&mut self) -> StorageIdx<NonSendStorage<T>> {
+        // Name the storage after `T` rather than its `SendWrapper`.
+        self.uniques
+            .lookup_or_insert_named(std::any::type_name::<T>())
+    }
+
+    #[inline]
+    #[cfg_attr(debug_assertions, track_caller)]
+    pub fn borrow_ref<T: Any>(
+        &self,
+        idx: StorageIdx<NonSendStorage<T>>,
+    ) -> BorrowResult<Ref<'_, NonSendStorage<T>>> {
+        self.check_thread::<T>()?;
+        self.uniques.borrow_ref(idx)
+    }
+
+    #[inline]
+    #[cfg_attr(debug_assertions, track_caller)]
+    pub fn borrow_mut<T: Any>(
+        &self,
+        idx: StorageIdx<NonSendStorage<T>>,
+    ) -> BorrowResult<RefMut<'_, NonSendStorage<T>>> {
+        self.check_thread::<T>()?;
+        self.uniques.borrow_mut(idx)
+    }
+}
@@ -1,12 +1,15 @@
 use std::any::Any;
-use std::cell::{Ref, RefMut};
 
 use super::erased::*;
 use crate::prelude::*;
 use crate::storage::unique::UniqueStorage;
 
-impl<T: Any> ErasableStorage for UniqueStorage<T> {
-    type ErasedStorage = Box<dyn Any>;
+impl<T: Any + Send + Sync> ErasableStorage for UniqueStorage<T> {
+    type ErasedStorage = Box<dyn Any + Send + Sync>;
+
+    fn type_name() -> &'static str {
+        std::any::type_name::<T>()
+    }
 
     fn erase(self) -> Self::ErasedStorage {
         Box::new(self)
@@ -22,32 +25,79 @@ impl<T: Any> ErasableStorage for UniqueStorage<T> {
 }
 
 #[derive(Default)]
-pub struct AllUniqueStorages(ErasedStorages<Box<dyn Any>>);
+pub struct AllUniqueStorages(ErasedStorages<Box<dyn Any + Send + Sync>>);
 
 impl AllUniqueStorages {
+    /// Insert a unique if it doesn't already exist.
+    pub fn insert<T: Any + Send + Sync>(&mut self, unique: T) -> Option<()> {
+        let storage = self.storage_mut::<T>();
+        match storage.0 {
+            Some(_) => None,
+            None => {
+                storage.0 = Some(unique);
+                Some(())
+            }
+        }
+    }
+
+    /// Insert a unique, returning the previous value if there was one.
+    #[inline]
+    pub fn replace<T: Any + Send + Sync>(&mut self, unique: T) -> Option<T> {
+        self.storage_mut::<T>().0.replace(unique)
+    }
+
+    #[inline]
+    pub fn remove<T: Any + Send + Sync>(&mut self) -> Option<T> {
+        let idx = self.lookup::<T>().ok()?;
+        self.0.get_mut(idx).0.take()
+    }
+
     #[inline]
-    pub fn insert<T: Any>(&mut self, unique: T) -> Option<()> {
-        self.0.insert(UniqueStorage(unique)).map(|_| {})
+    pub fn get_or_insert_with<T: Any + Send + Sync>(&mut self, f: impl FnOnce() -> T) -> &mut T {
+        self.storage_mut::<T>().0.get_or_insert_with(f)
     }
 
     #[inline]
-    pub fn lookup<T: Any>(&self) -> BorrowResult<StorageIdx<UniqueStorage<T>>> {
+    fn storage_mut<T: Any + Send + Sync>(&mut self) -> &mut UniqueStorage<T> {
+        let idx = self.0.lookup_or_insert();
+        self.0.get_mut(idx)
+    }
+
+    #[inline]
+    pub fn lookup<T: Any + Send + Sync>(&self) -> BorrowResult<StorageIdx<UniqueStorage<T>>> {
         self.0.lookup()
     }
 
     #[inline]
-    pub fn borrow_ref<T: Any>(
+    pub fn lookup_or_insert<T: Any + Send + Sync>(&mut self) -> StorageIdx<UniqueStorage<T>> {
+        self.0.lookup_or_insert()
+    }
+
+    /// Like [`AllUniqueStorages::lookup_or_insert`], but naming the unique
+    /// in errors with `type_name` if its storage is created.
+    #[inline]
+    pub(crate) fn lookup_or_insert_named<T: Any + Send + Sync>(
+        &mut self,
+        type_name: &'static str,
+    ) -> StorageIdx<UniqueStorage<T>> {
+        self.0.lookup_or_insert_named(type_name)
+    }
+
+    #[inline]
+    #[cfg_attr(debug_assertions, track_caller)]
+    pub fn borrow_ref<T: Any + Send + Sync>(
         &self,
         idx: StorageIdx<UniqueStorage<T>>,
-    ) -> BorrowResult<Ref<UniqueStorage<T>>> {
+    ) -> BorrowResult<Ref<'_, UniqueStorage<T>>> {
         self.0.borrow_ref(idx)
     }
 
     #[inline]
-    pub fn borrow_mut<T: Any>(
+    #[cfg_attr(debug_assertions, track_caller)]
+    pub fn borrow_mut<T: Any + Send + Sync>(
         &self,
         idx: StorageIdx<UniqueStorage<T>>,
-    ) -> BorrowResult<RefMut<UniqueStorage<T>>> {
+    ) -> BorrowResult<RefMut<'_, UniqueStorage<T>>> {
         self.0.borrow_mut(idx)
     }
 }
@@ -0,0 +1,41 @@
+use crate::prelude::*;
+
+/// A group of components that are inserted and removed together.
+///
+/// Implemented for tuples of up to 8 components, and for structs with
+/// `#[derive(Bundle)]`.
+pub trait Bundle: Send + Sync + 'static {
+    /// Add every component of the bundle to an entity.
+    fn insert_into<'a>(self, entity: EntityMut<'a>) -> EntityMut<'a>;
+
+    /// Remove every component of the bundle from an entity.
+    fn remove_from<'a>(entity: EntityMut<'a>) -> EntityMut<'a>;
+}
+
+macro_rules! impl_bundle {
+    ($($component:ident),*) => {
+        impl<$($component: Component),*> Bundle for ($($component,)*) {
+            #[inline]
+            #[allow(non_snake_case)]
+            fn insert_into<'a>(self, entity: EntityMut<'a>) -> EntityMut<'a> {
+                let ($($component,)*) = self;
+                entity$(.insert($component))*
+            }
+
+            #[inline]
+            fn remove_from<'a>(entity: EntityMut<'a>) -> EntityMut<'a> {
+                entity$(.remove::<$component>())*
+            }
+        }
+    };
+}
+
+impl_bundle!();
+impl_bundle!(C0);
+impl_bundle!(C0, C1);
+impl_bundle!(C0, C1, C2);
+impl_bundle!(C0, C1, C2, C3);
+impl_bundle!(C0, C1, C2, C3, C4);
+impl_bundle!(C0, C1, C2, C3, C4, C5);
+impl_bundle!(C0, C1, C2, C3, C4, C5, C6);
+impl_bundle!(C0, C1, C2, C3, C4, C5, C6, C7);
@@ -0,0 +1,73 @@
+use crate::prelude::*;
+
+type Command = Box<dyn FnOnce(&mut World) + Send + Sync>;
+
+/// A queue of operations to apply to the world later.
+///
+/// Commands are applied when the world is flushed.
+#[derive(Default)]
+pub struct Commands {
+    queue: Vec<Command>,
+}
+
+impl Commands {
+    /// Queue an arbitrary operation.
+    #[inline]
+    pub fn push(&mut self, command: impl FnOnce(&mut World) + Send + Sync + 'static) {
+        self.queue.push(Box::new(command));
+    }
+
+    /// Queue adding a component to an entity.
+    ///
+    /// Does nothing if the entity is dead by the time the command is applied.
+    pub fn insert<C: Component>(&mut self, entity: EntityId, component: C) {
+        self.push(move |world| {
+            if world.all_storages.entities.is_alive(entity) {
+                world.entity(entity).insert(component);
+            }
+        });
+    }
+
+    /// Queue removing a component from an entity.
+    ///
+    /// Does nothing if the entity is dead by the time the command is applied.
+    pub fn remove<C: Component>(&mut self, entity: EntityId) {
+        self.push(move |world| {
+            if world.all_storages.entities.is_alive(entity) {
+                world.entity(entity).remove::<C>();
+            }
+        });
+    }
+
+    /// Queue despawning an entity.
+    ///
+    /// Does nothing if the entity is dead by the time the command is applied.
+    /// As there's nowhere to return an error to, applying the command panics
+    /// if the entity ran out of versions under [`GenerationOverflow::Error`];
+    /// queue [`EntityMut::despawn`] with [`Commands::push`] to handle that.
+    pub fn despawn(&mut self, entity: EntityId) {
+        self.push(move |world| {
+            if world.all_storages.entities.is_alive(entity) {
+                if let Err(error) = world.entity(entity).despawn() {
+                    panic!("{error}");
+                }
+            }
+        });
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.queue.is_empty()
+    }
+
+    #[inline]
+    pub(crate) fn append(&mut self, other: &mut Commands) {
+        self.queue.append(&mut other.queue);
+    }
+
+    pub(crate) fn apply(self, world: &mut World) {
+        for command in self.queue {
+            command(world);
+        }
+    }
+}
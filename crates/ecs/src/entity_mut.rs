@@ -1,5 +1,3 @@
-use std::cell::RefMut;
-
 use crate::all_storages::{AllComponentStorages, AllStorages};
 use crate::prelude::*;
 
@@ -19,23 +17,41 @@ impl<'a> EntityMut<'a> {
 
     /// Despawn an entity.
     ///
+    /// Returns an error without touching the entity if it ran out of
+    /// versions under [`GenerationOverflow::Error`].
+    ///
     /// Panics if the entity is dead or any storage is borrowed.
-    pub fn despawn(self) {
-        self.all_storages.despawn_entity(self.entity);
+    pub fn despawn(self) -> Result<(), GenerationOverflowError> {
+        self.all_storages.despawn_entity(self.entity)
     }
 
     /// Add a component to the entity.
     ///
     /// Panics if the component type is not registered.
-    pub fn insert<C: Component>(self, component: C) -> Self {
-        let mut components = lookup_or_insert_and_borrow_mut(&mut self.all_storages.components);
+    ///
+    /// Any components required by `C` that the entity doesn't have are
+    /// inserted too.
+    pub fn insert<C: Component>(mut self, component: C) -> Self {
+        self.insert_component(component);
+        self
+    }
+
+    pub(crate) fn insert_component<C: Component>(&mut self, component: C) {
+        let idx = self.all_storages.register_component::<C>();
+        let components = self.all_storages.components.get_mut(idx);
         let entity = self.all_storages.entities.entity_to_alive(self.entity);
 
         components.insert(&entity, component);
 
-        drop(components);
+        let mut required = RequiredComponents::default();
+        C::required(&mut required);
+        required.insert_into(self);
+    }
 
-        self
+    /// Add every component of a bundle to the entity.
+    #[inline]
+    pub fn insert_bundle<B: Bundle>(self, bundle: B) -> Self {
+        bundle.insert_into(self)
     }
 
     /// Remove a component from an entity.
@@ -50,6 +66,43 @@ impl<'a> EntityMut<'a> {
         self
     }
 
+    /// Remove every component of a bundle from the entity.
+    #[inline]
+    pub fn remove_bundle<B: Bundle>(self) -> Self {
+        B::remove_from(self)
+    }
+
+    /// Disable the entity, so that it is skipped by joins.
+    ///
+    /// The entity keeps its components.
+    #[inline]
+    pub fn disable(self) -> Self {
+        self.insert(Disabled)
+    }
+
+    /// Enable the entity after it was disabled.
+    #[inline]
+    pub fn enable(self) -> Self {
+        self.remove::<Disabled>()
+    }
+
+    #[inline]
+    pub fn is_disabled(&self) -> bool {
+        self.contains::<Disabled>()
+    }
+
+    /// Check if the entity has a component.
+    #[inline]
+    pub fn contains<C: Component>(&self) -> bool {
+        self.all_storages.contains_component::<C>(self.entity)
+    }
+
+    /// Iterate over the types of the components the entity has.
+    #[inline]
+    pub fn components(&self) -> impl Iterator<Item = &ComponentInfo> + '_ {
+        self.all_storages.components_of(self.entity)
+    }
+
     /// Get the entity's id.
     #[inline]
     pub fn id(&self) -> EntityId {
@@ -59,19 +112,10 @@ impl<'a> EntityMut<'a> {
 
 fn lookup_and_borrow_mut<C: Component>(
     all_components: &mut AllComponentStorages,
-) -> Option<RefMut<ComponentStorage<C>>> {
+) -> Option<RefMut<'_, ComponentStorage<C>>> {
     let idx = all_components.lookup().ok()?;
     let components = all_components
         .borrow_mut(idx)
         .expect("component type not registered");
     Some(components)
 }
-
-fn lookup_or_insert_and_borrow_mut<C: Component>(
-    all_components: &mut AllComponentStorages,
-) -> RefMut<ComponentStorage<C>> {
-    let idx = all_components.lookup_or_insert();
-    all_components
-        .borrow_mut(idx)
-        .expect("component type not registered")
-}
@@ -0,0 +1,55 @@
+use crate::all_storages::AllStorages;
+use crate::prelude::*;
+
+/// A handle to read an entity.
+pub struct EntityRef<'a> {
+    all_storages: &'a AllStorages,
+    entity: EntityId,
+}
+
+impl<'a> EntityRef<'a> {
+    pub(crate) fn new(entity: EntityId, all_storages: &'a AllStorages) -> Self {
+        Self {
+            entity,
+            all_storages,
+        }
+    }
+
+    /// Get one of the entity's components.
+    ///
+    /// Panics if the component's storage is borrowed mutably.
+    pub fn get<C: Component>(&self) -> Option<Ref<'a, C>> {
+        let idx = self.all_storages.components.lookup::<C>().ok()?;
+        let components = self
+            .all_storages
+            .components
+            .borrow_ref(idx)
+            .expect("couldn't borrow storage");
+        let entity = self.all_storages.entities.entity_to_alive(self.entity);
+
+        Ref::try_map(components, |components| components.get(&entity)).ok()
+    }
+
+    #[inline]
+    pub fn is_disabled(&self) -> bool {
+        self.contains::<Disabled>()
+    }
+
+    /// Check if the entity has a component.
+    #[inline]
+    pub fn contains<C: Component>(&self) -> bool {
+        self.all_storages.contains_component::<C>(self.entity)
+    }
+
+    /// Iterate over the types of the components the entity has.
+    #[inline]
+    pub fn components(&self) -> impl Iterator<Item = &'a ComponentInfo> + 'a {
+        self.all_storages.components_of(self.entity)
+    }
+
+    /// Get the entity's id.
+    #[inline]
+    pub fn id(&self) -> EntityId {
+        self.entity
+    }
+}
@@ -1,19 +1,40 @@
+pub mod access;
 pub mod all_storages;
+pub mod bundle;
+pub mod commands;
 pub mod entity_mut;
+pub mod entity_ref;
+pub mod pipe;
 pub mod query;
+pub mod schedule;
 pub mod storage;
 pub mod system;
 pub mod world;
 
 pub mod prelude {
+    pub use access::*;
+    pub use bundle::Bundle;
+    pub use commands::*;
     pub use entity_mut::EntityMut;
+    pub use entity_ref::EntityRef;
+    pub use pipe::*;
     pub use query::*;
+    pub use schedule::*;
     pub use storage::components::*;
     pub use storage::entities::*;
+    pub use storage::index::{DuplicateKeyError, Indexed};
+    pub use storage::non_send::NonSendComponent;
     pub use storage::unique::*;
-    pub use storage::{BorrowError, BorrowResult, Iter, IterMut};
+    pub use storage::{
+        BorrowError, BorrowKind, BorrowResult, Iter, IterMut, MemoryUsage, Ref, RefMut,
+    };
     pub use system::*;
     pub use world::*;
 
+    pub use ecs_derive::{Bundle, Component, SystemParam};
+
+    #[cfg(feature = "parallel")]
+    pub use rayon::iter::{IndexedParallelIterator, ParallelIterator};
+
     use super::*;
 }
@@ -0,0 +1,242 @@
+use std::marker::PhantomData;
+
+use crate::prelude::*;
+
+/// Marker for the parameters of systems built from other systems.
+pub struct Adapted<Params>(PhantomData<Params>);
+
+/// Extension methods for combining systems.
+pub trait SystemExt<Params, Output: SystemOutput>: System<Params, Output> + Sized {
+    /// Pass the output of this system into `next`, which takes it as an
+    /// [`In`] parameter.
+    ///
+    /// `next` doesn't run if this system fails.
+    fn pipe<Next, NextParams, NextOutput>(
+        self,
+        next: Next,
+    ) -> Pipe<Self, Params, Output, Next, NextParams, NextOutput>
+    where
+        Next: InputSystem<Output::Success, NextParams, NextOutput>,
+        NextOutput: SystemOutput,
+        Output::Error: Into<NextOutput::Error>,
+    {
+        // Built once here, since systems are named with static strings.
+        let name = format!("{} | {}", self.name(), next.name());
+        Pipe {
+            first: self,
+            second: next,
+            name: Box::leak(name.into_boxed_str()),
+            phantom_data: PhantomData,
+        }
+    }
+
+    /// Transform the system's execution error (e.g. by logging it).
+    fn map_err<F, Error>(self, f: F) -> MapErr<Self, Params, Output, F>
+    where
+        F: FnMut(Output::Error) -> Error,
+    {
+        MapErr {
+            system: self,
+            f,
+            phantom_data: PhantomData,
+        }
+    }
+
+    /// Discard the system's output, including any execution error.
+    ///
+    /// Borrow errors are still reported.
+    fn ignore(self) -> Ignore<Self, Params, Output> {
+        Ignore {
+            system: self,
+            phantom_data: PhantomData,
+        }
+    }
+
+    /// Use the default value of the system's output if it fails to execute.
+    ///
+    /// Borrow errors are still reported.
+    fn unwrap_or_default(self) -> UnwrapOrDefault<Self, Params, Output>
+    where
+        Output::Success: Default,
+    {
+        UnwrapOrDefault {
+            system: self,
+            phantom_data: PhantomData,
+        }
+    }
+}
+
+impl<S: System<Params, Output>, Params, Output: SystemOutput> SystemExt<Params, Output> for S {}
+
+/// A system that passes the output of one system into another.
+#[allow(clippy::type_complexity)]
+pub struct Pipe<First, FirstParams, FirstOutput, Second, SecondParams, SecondOutput> {
+    first: First,
+    second: Second,
+    name: &'static str,
+    phantom_data: PhantomData<fn() -> (FirstParams, FirstOutput, SecondParams, SecondOutput)>,
+}
+
+impl<First, FirstParams, FirstOutput, Second, SecondParams, SecondOutput>
+    System<Adapted<(FirstParams, SecondParams)>, Result<SecondOutput::Success, SecondOutput::Error>>
+    for Pipe<First, FirstParams, FirstOutput, Second, SecondParams, SecondOutput>
+where
+    First: System<FirstParams, FirstOutput>,
+    FirstOutput: SystemOutput,
+    FirstOutput::Error: Into<SecondOutput::Error>,
+    Second: InputSystem<FirstOutput::Success, SecondParams, SecondOutput>,
+    SecondOutput: SystemOutput,
+{
+    fn run(
+        &mut self,
+        world: &mut World,
+    ) -> SystemResult<SecondOutput::Success, SecondOutput::Error> {
+        let input = self.first.run(world).map_err(|err| err.map(Into::into))?;
+        self.second.run_with(input, world)
+    }
+
+    #[inline]
+    fn is_send(&self) -> bool {
+        self.first.is_send() && self.second.is_send()
+    }
+
+    // The two systems never borrow at the same time.
+    fn access(&self) -> Result<Access, AccessConflict> {
+        let mut access = self.first.access()?;
+        access.extend(&self.second.access()?);
+        Ok(access)
+    }
+
+    #[inline]
+    fn is_exclusive(&self) -> bool {
+        self.first.is_exclusive() || self.second.is_exclusive()
+    }
+
+    /// Named after both systems, as `first | second`.
+    #[inline]
+    fn name(&self) -> &'static str {
+        self.name
+    }
+}
+
+/// A system that transforms another system's execution error.
+pub struct MapErr<S, Params, Output, F> {
+    system: S,
+    f: F,
+    phantom_data: PhantomData<fn() -> (Params, Output)>,
+}
+
+impl<S, Params, Output, F, Error> System<Adapted<Params>, Result<Output::Success, Error>>
+    for MapErr<S, Params, Output, F>
+where
+    S: System<Params, Output>,
+    Output: SystemOutput,
+    F: FnMut(Output::Error) -> Error,
+{
+    fn run(&mut self, world: &mut World) -> SystemResult<Output::Success, Error> {
+        self.system.run(world).map_err(|err| err.map(&mut self.f))
+    }
+
+    #[inline]
+    fn is_send(&self) -> bool {
+        self.system.is_send()
+    }
+
+    #[inline]
+    fn access(&self) -> Result<Access, AccessConflict> {
+        self.system.access()
+    }
+
+    #[inline]
+    fn is_exclusive(&self) -> bool {
+        self.system.is_exclusive()
+    }
+
+    #[inline]
+    fn name(&self) -> &'static str {
+        self.system.name()
+    }
+}
+
+/// A system that discards another system's output.
+pub struct Ignore<S, Params, Output> {
+    system: S,
+    phantom_data: PhantomData<fn() -> (Params, Output)>,
+}
+
+impl<S, Params, Output> System<Adapted<Params>, Result<(), Output::Error>>
+    for Ignore<S, Params, Output>
+where
+    S: System<Params, Output>,
+    Output: SystemOutput,
+{
+    fn run(&mut self, world: &mut World) -> SystemResult<(), Output::Error> {
+        match self.system.run(world) {
+            Ok(_) | Err(SystemError::ExecutionError(_)) => Ok(()),
+            Err(SystemError::BorrowError(err)) => Err(SystemError::BorrowError(err)),
+        }
+    }
+
+    #[inline]
+    fn is_send(&self) -> bool {
+        self.system.is_send()
+    }
+
+    #[inline]
+    fn access(&self) -> Result<Access, AccessConflict> {
+        self.system.access()
+    }
+
+    #[inline]
+    fn is_exclusive(&self) -> bool {
+        self.system.is_exclusive()
+    }
+
+    #[inline]
+    fn name(&self) -> &'static str {
+        self.system.name()
+    }
+}
+
+/// A system that replaces another system's execution errors with a default
+/// value.
+pub struct UnwrapOrDefault<S, Params, Output> {
+    system: S,
+    phantom_data: PhantomData<fn() -> (Params, Output)>,
+}
+
+impl<S, Params, Output> System<Adapted<Params>, Result<Output::Success, Output::Error>>
+    for UnwrapOrDefault<S, Params, Output>
+where
+    S: System<Params, Output>,
+    Output: SystemOutput,
+    Output::Success: Default,
+{
+    fn run(&mut self, world: &mut World) -> SystemResult<Output::Success, Output::Error> {
+        match self.system.run(world) {
+            Ok(success) => Ok(success),
+            Err(SystemError::ExecutionError(_)) => Ok(Output::Success::default()),
+            Err(SystemError::BorrowError(err)) => Err(SystemError::BorrowError(err)),
+        }
+    }
+
+    #[inline]
+    fn is_send(&self) -> bool {
+        self.system.is_send()
+    }
+
+    #[inline]
+    fn access(&self) -> Result<Access, AccessConflict> {
+        self.system.access()
+    }
+
+    #[inline]
+    fn is_exclusive(&self) -> bool {
+        self.system.is_exclusive()
+    }
+
+    #[inline]
+    fn name(&self) -> &'static str {
+        self.system.name()
+    }
+}
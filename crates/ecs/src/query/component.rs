@@ -1,4 +1,4 @@
-use std::cell::{Ref, RefMut};
+use std::cmp::Ordering;
 
 use crate::all_storages::erased::StorageIdx;
 use crate::prelude::*;
@@ -9,36 +9,73 @@ pub struct Comp<'a, C: Component> {
 }
 
 pub struct CompMut<'a, C: Component> {
-    storage: RefMut<'a, ComponentStorage<C>>,
-    entities: &'a EntityStorage,
+    pub(super) storage: RefMut<'a, ComponentStorage<C>>,
+    pub(super) entities: &'a EntityStorage,
 }
 
 impl<'a, C: Component> Comp<'a, C> {
     #[inline]
     pub fn get(&self, entity: EntityId) -> Option<&C> {
-        self.storage.get(&self.entities.entity_to_alive(entity))
+        self.entities
+            .entity_to_spawned(entity)
+            .and_then(|entity| self.storage.get(&entity))
+    }
+
+    /// Iterate over the components of enabled entities.
+    #[inline]
+    pub fn iter(&self) -> impl Iterator<Item = &C> + '_ {
+        enabled(self.entities, self.storage.indexed_iter())
     }
 
+    /// Iterate over all components, including those of disabled entities.
     #[inline]
-    pub fn iter(&self) -> Iter<C> {
+    pub fn iter_with_disabled(&self) -> Iter<'_, C> {
         self.storage.iter()
     }
 
+    /// The components as a contiguous slice, in the same order as
+    /// [`Comp::ids_with_disabled`].
+    ///
+    /// This is raw storage access, so it includes disabled entities.
+    #[inline]
+    pub fn as_slice(&self) -> &[C] {
+        self.storage.as_slice()
+    }
+
+    /// The enabled entities with this component, in iteration order.
+    #[inline]
+    pub fn ids(&self) -> impl Iterator<Item = EntityId> + '_ {
+        enabled(self.entities, ids(&self.storage, self.entities))
+    }
+
+    /// The entities with this component, including disabled ones, in
+    /// iteration order.
+    #[inline]
+    pub fn ids_with_disabled(&self) -> impl Iterator<Item = EntityId> + '_ {
+        ids(&self.storage, self.entities).map(|(_, id)| id)
+    }
+
     #[inline]
     pub fn contains(&self, entity: EntityId) -> bool {
-        self.storage.contains(self.entities.entity_to_alive(entity))
+        self.entities
+            .entity_to_spawned(entity)
+            .is_some_and(|entity| self.storage.contains(entity))
     }
 }
 
 impl<'a, C: Component> CompMut<'a, C> {
     #[inline]
     pub fn get(&self, entity: EntityId) -> Option<&C> {
-        self.storage.get(&self.entities.entity_to_alive(entity))
+        self.entities
+            .entity_to_spawned(entity)
+            .and_then(|entity| self.storage.get(&entity))
     }
 
     #[inline]
     pub fn get_mut(&mut self, entity: EntityId) -> Option<&mut C> {
-        self.storage.get_mut(&self.entities.entity_to_alive(entity))
+        self.entities
+            .entity_to_spawned(entity)
+            .and_then(|entity| self.storage.get_mut(&entity))
     }
 
     #[inline]
@@ -49,55 +86,243 @@ impl<'a, C: Component> CompMut<'a, C> {
 
     #[inline]
     pub fn remove(&mut self, entity: EntityId) -> Option<C> {
-        self.storage.remove(&self.entities.entity_to_alive(entity))
+        self.entities
+            .entity_to_spawned(entity)
+            .and_then(|entity| self.storage.remove(&entity))
+    }
+
+    /// Iterate over the components of enabled entities.
+    #[inline]
+    pub fn iter(&self) -> impl Iterator<Item = &C> + '_ {
+        enabled(self.entities, self.storage.indexed_iter())
     }
 
+    /// Iterate over all components, including those of disabled entities.
     #[inline]
-    pub fn iter(&self) -> Iter<C> {
+    pub fn iter_with_disabled(&self) -> Iter<'_, C> {
         self.storage.iter()
     }
 
+    /// Mutably iterate over the components of enabled entities.
     #[inline]
-    pub fn iter_mut(&mut self) -> IterMut<C> {
+    pub fn iter_mut(&mut self) -> impl Iterator<Item = &mut C> + '_ {
+        enabled(self.entities, self.storage.indexed_iter_mut())
+    }
+
+    /// Mutably iterate over all components, including those of disabled
+    /// entities.
+    #[inline]
+    pub fn iter_mut_with_disabled(&mut self) -> IterMut<'_, C> {
         self.storage.iter_mut()
     }
 
+    /// The components as a contiguous slice, in the same order as
+    /// [`CompMut::ids_with_disabled`].
+    ///
+    /// This is raw storage access, so it includes disabled entities.
+    #[inline]
+    pub fn as_slice(&self) -> &[C] {
+        self.storage.as_slice()
+    }
+
+    /// Like [`CompMut::as_slice`], this includes disabled entities.
+    #[inline]
+    pub fn as_mut_slice(&mut self) -> &mut [C] {
+        self.storage.as_mut_slice()
+    }
+
+    /// The enabled entities with this component, in iteration order.
+    #[inline]
+    pub fn ids(&self) -> impl Iterator<Item = EntityId> + '_ {
+        enabled(self.entities, ids(&self.storage, self.entities))
+    }
+
+    /// The entities with this component, including disabled ones, in
+    /// iteration order.
+    #[inline]
+    pub fn ids_with_disabled(&self) -> impl Iterator<Item = EntityId> + '_ {
+        ids(&self.storage, self.entities).map(|(_, id)| id)
+    }
+
     #[inline]
     pub fn contains(&self, entity: EntityId) -> bool {
-        self.storage.contains(self.entities.entity_to_alive(entity))
+        self.entities
+            .entity_to_spawned(entity)
+            .is_some_and(|entity| self.storage.contains(entity))
+    }
+
+    /// Remove every component, as if each was removed individually.
+    #[inline]
+    pub fn clear(&mut self) {
+        self.storage.clear(self.entities);
+    }
+
+    #[inline]
+    pub fn reserve(&mut self, additional: usize) {
+        self.storage.reserve(additional);
+    }
+
+    #[inline]
+    pub fn shrink_to_fit(&mut self) {
+        self.storage.shrink_to_fit();
+    }
+
+    #[inline]
+    pub fn sort_by(&mut self, compare: impl FnMut(&C, &C) -> Ordering) {
+        self.storage.sort_by(compare);
+    }
+
+    #[inline]
+    pub fn sort_by_key<K: Ord>(&mut self, key: impl FnMut(&C) -> K) {
+        self.storage.sort_by_key(key);
+    }
+
+    /// Order the components by the entity order of another storage, such as
+    /// a [`Comp`] or [`CompMut`].
+    #[inline]
+    pub fn sort_like<O: Component>(&mut self, other: &impl AsRef<ComponentStorage<O>>) {
+        self.storage.sort_like(other.as_ref());
+    }
+}
+
+/// The entities in a storage, with their indices.
+fn ids<'a, C: Component>(
+    storage: &'a ComponentStorage<C>,
+    entities: &'a EntityStorage,
+) -> impl Iterator<Item = (usize, EntityId)> + 'a {
+    let mut cursor = 0;
+    std::iter::from_fn(move || storage.next_index(&mut cursor))
+        .map(|index| (index, entities.id_at(index)))
+}
+
+/// Skip the items of disabled entities, given each item's entity index.
+fn enabled<'a, T>(
+    entities: &'a EntityStorage,
+    items: impl Iterator<Item = (usize, T)> + 'a,
+) -> impl Iterator<Item = T> + 'a {
+    let (word, bit) = (Disabled::ID.index() / 64, Disabled::ID.index() % 64);
+    items
+        .filter(move |&(index, _)| entities.signature_word(index, word) & (1 << bit) == 0)
+        .map(|(_, item)| item)
+}
+
+impl<'a, C: Component> AsRef<ComponentStorage<C>> for Comp<'a, C> {
+    #[inline]
+    fn as_ref(&self) -> &ComponentStorage<C> {
+        &self.storage
+    }
+}
+
+impl<'a, C: Component> AsRef<ComponentStorage<C>> for CompMut<'a, C> {
+    #[inline]
+    fn as_ref(&self) -> &ComponentStorage<C> {
+        &self.storage
+    }
+}
+
+impl<'a, C: Component> Drop for CompMut<'a, C> {
+    fn drop(&mut self) {
+        // Components may have been modified through `get_mut` or `iter_mut`.
+        self.storage.update_index();
     }
 }
 
-impl<'a, C: Component> Query<'a> for Comp<'a, C> {
+impl<'q, C: Component> Query for Comp<'q, C> {
     type Index = StorageIdx<ComponentStorage<C>>;
+    type Item<'a> = Comp<'a, C>;
+
+    #[inline]
+    fn is_send() -> bool {
+        C::IS_SEND
+    }
 
     #[inline]
     fn lookup(world: &mut World) -> Self::Index {
-        world.all_storages.components.lookup_or_insert()
+        world.all_storages.register_component()
     }
 
     #[inline]
-    fn borrow(world: &'a World, idx: Self::Index) -> BorrowResult<Self> {
-        Ok(Self {
+    fn borrow<'a>(world: &'a World, idx: Self::Index) -> BorrowResult<Self::Item<'a>> {
+        Ok(Comp {
             storage: world.all_storages.components.borrow_ref(idx)?,
             entities: &world.all_storages.entities,
         })
     }
+
+    #[inline]
+    fn access(access: &mut Access) -> Result<(), AccessConflict> {
+        access.read::<ComponentStorage<C>>(std::any::type_name::<C>())
+    }
 }
 
-impl<'a, C: Component> Query<'a> for CompMut<'a, C> {
+impl<'q, C: Component> Query for CompMut<'q, C> {
     type Index = StorageIdx<ComponentStorage<C>>;
+    type Item<'a> = CompMut<'a, C>;
+
+    #[inline]
+    fn is_send() -> bool {
+        C::IS_SEND
+    }
 
     #[inline]
     fn lookup(world: &mut World) -> Self::Index {
-        world.all_storages.components.lookup_or_insert()
+        world.all_storages.register_component()
     }
 
     #[inline]
-    fn borrow(world: &'a World, idx: Self::Index) -> BorrowResult<Self> {
-        Ok(Self {
+    fn borrow<'a>(world: &'a World, idx: Self::Index) -> BorrowResult<Self::Item<'a>> {
+        Ok(CompMut {
             storage: world.all_storages.components.borrow_mut(idx)?,
             entities: &world.all_storages.entities,
         })
     }
+
+    #[inline]
+    fn access(access: &mut Access) -> Result<(), AccessConflict> {
+        access.write::<ComponentStorage<C>>(std::any::type_name::<C>())
+    }
+}
+
+macro_rules! impl_storage_join {
+    ($param:ty, $item:ty, $get:ident) => {
+        impl<'a, 'b, C: Component> JoinParam for $param {
+            #[inline]
+            fn filter(&self, filter: &mut JoinFilter) {
+                filter.with(self.storage.id());
+            }
+
+            #[inline]
+            fn driver_len(&self) -> Option<usize> {
+                Some(self.storage.len())
+            }
+
+            #[inline]
+            fn next_index(&self, cursor: &mut usize) -> Option<usize> {
+                self.storage.next_index(cursor)
+            }
+
+            #[inline]
+            fn matches(&self, index: usize, filter: &JoinFilter) -> bool {
+                filter.matches(|word| self.entities.signature_word(index, word))
+            }
+
+            #[inline]
+            fn entity_id(&self, index: usize) -> EntityId {
+                self.entities.id_at(index)
+            }
+        }
+
+        impl<'a, 'b, 'c, C: Component> JoinFetch<'c> for $param {
+            type Item = $item;
+
+            #[inline]
+            fn fetch(&'c mut self, index: usize) -> Self::Item {
+                self.storage.$get(index).unwrap()
+            }
+        }
+    };
 }
+
+impl_storage_join!(&'b Comp<'a, C>, &'c C, get_by_index);
+impl_storage_join!(&'b CompMut<'a, C>, &'c C, get_by_index);
+impl_storage_join!(&'b mut CompMut<'a, C>, &'c mut C, get_mut_by_index);
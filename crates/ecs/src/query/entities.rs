@@ -0,0 +1,50 @@
+use crate::prelude::*;
+
+/// Access to entities from inside a system.
+pub struct Entities<'a> {
+    entities: &'a EntityStorage,
+}
+
+impl<'a> Entities<'a> {
+    /// Reserve an entity id.
+    ///
+    /// The entity is spawned when the world is next flushed.
+    #[inline]
+    pub fn reserve(&self) -> EntityId {
+        self.entities.reserve()
+    }
+
+    /// Iterate over all enabled entities.
+    pub fn iter(&self) -> impl Iterator<Item = EntityId> + '_ {
+        self.iter_with_disabled().filter(|&entity| {
+            !self
+                .entities
+                .entity_to_alive(entity)
+                .has_component(Disabled::ID)
+        })
+    }
+
+    /// Iterate over all entities, including disabled ones.
+    pub fn iter_with_disabled(&self) -> impl Iterator<Item = EntityId> + '_ {
+        self.entities.alive().into_iter()
+    }
+}
+
+impl<'q> Query for Entities<'q> {
+    type Index = ();
+    type Item<'a> = Entities<'a>;
+
+    #[inline]
+    fn lookup(_world: &mut World) -> Self::Index {}
+
+    #[inline]
+    fn borrow<'a>(world: &'a World, _idx: Self::Index) -> BorrowResult<Self::Item<'a>> {
+        Ok(Entities {
+            entities: &world.all_storages.entities,
+        })
+    }
+    #[inline]
+    fn access(_access: &mut Access) -> Result<(), AccessConflict> {
+        Ok(())
+    }
+}
@@ -0,0 +1,83 @@
+use std::marker::PhantomData;
+
+use crate::prelude::*;
+
+/// A join filter for entities that have a component, without borrowing the
+/// component's storage.
+pub struct With<'a, C: Component> {
+    id: ComponentId,
+    entities: &'a EntityStorage,
+    phantom_data: PhantomData<fn() -> C>,
+}
+
+/// A join filter for entities that don't have a component.
+pub struct Without<'a, C: Component> {
+    id: ComponentId,
+    entities: &'a EntityStorage,
+    phantom_data: PhantomData<fn() -> C>,
+}
+
+macro_rules! impl_filter {
+    ($filter:ident, $method:ident) => {
+        impl<'q, C: Component> Query for $filter<'q, C> {
+            type Index = ComponentId;
+            type Item<'a> = $filter<'a, C>;
+
+            #[inline]
+            fn lookup(world: &mut World) -> Self::Index {
+                ComponentId(world.all_storages.register_component::<C>().index())
+            }
+
+            #[inline]
+            fn borrow<'a>(world: &'a World, id: Self::Index) -> BorrowResult<Self::Item<'a>> {
+                Ok($filter {
+                    id,
+                    entities: &world.all_storages.entities,
+                    phantom_data: PhantomData,
+                })
+            }
+
+            // Filters only read entity signatures, not the storage.
+            #[inline]
+            fn access(_access: &mut Access) -> Result<(), AccessConflict> {
+                Ok(())
+            }
+        }
+
+        impl<'a, 'b, C: Component> JoinParam for &'b $filter<'a, C> {
+            #[inline]
+            fn filter(&self, filter: &mut JoinFilter) {
+                filter.$method(self.id);
+            }
+
+            #[inline]
+            fn driver_len(&self) -> Option<usize> {
+                None
+            }
+
+            fn next_index(&self, _cursor: &mut usize) -> Option<usize> {
+                unreachable!("filters can't be iterated over")
+            }
+
+            #[inline]
+            fn matches(&self, index: usize, filter: &JoinFilter) -> bool {
+                filter.matches(|word| self.entities.signature_word(index, word))
+            }
+
+            #[inline]
+            fn entity_id(&self, index: usize) -> EntityId {
+                self.entities.id_at(index)
+            }
+        }
+
+        impl<'a, 'b, 'c, C: Component> JoinFetch<'c> for &'b $filter<'a, C> {
+            type Item = ();
+
+            #[inline]
+            fn fetch(&'c mut self, _index: usize) -> Self::Item {}
+        }
+    };
+}
+
+impl_filter!(With, with);
+impl_filter!(Without, without);
@@ -0,0 +1,69 @@
+use crate::all_storages::erased::StorageIdx;
+use crate::prelude::*;
+
+/// Looks up entities by the key of an [`Indexed`] component.
+pub struct KeyIndex<'a, C: Indexed> {
+    storage: Ref<'a, ComponentStorage<C>>,
+}
+
+impl<'a, C: Indexed> KeyIndex<'a, C> {
+    /// Get the entity with a key, for keys that are unique.
+    ///
+    /// If several entities share the key, the one indexed first is returned,
+    /// unless the component is [`Indexed::UNIQUE`], in which case it's an
+    /// error.
+    #[inline]
+    pub fn get(&self, key: &C::Key) -> Result<Option<EntityId>, DuplicateKeyError> {
+        match self.get_all(key) {
+            entities @ [_, _, ..] if C::UNIQUE => Err(DuplicateKeyError {
+                type_name: std::any::type_name::<C>(),
+                entities: entities.to_vec(),
+            }),
+            entities => Ok(entities.first().copied()),
+        }
+    }
+
+    /// Get every entity with a key.
+    #[inline]
+    pub fn get_all(&self, key: &C::Key) -> &[EntityId] {
+        self.storage.key_index().unwrap().get_all(key)
+    }
+
+    #[inline]
+    pub fn contains_key(&self, key: &C::Key) -> bool {
+        !self.get_all(key).is_empty()
+    }
+}
+
+impl<'q, C: Indexed> Query for KeyIndex<'q, C> {
+    type Index = StorageIdx<ComponentStorage<C>>;
+    type Item<'a> = KeyIndex<'a, C>;
+
+    #[inline]
+    fn is_send() -> bool {
+        C::IS_SEND
+    }
+
+    #[inline]
+    fn lookup(world: &mut World) -> Self::Index {
+        let idx = world.all_storages.register_component();
+        let all_storages = &mut world.all_storages;
+        all_storages
+            .components
+            .get_mut(idx)
+            .build_index(&all_storages.entities);
+        idx
+    }
+
+    #[inline]
+    fn borrow<'a>(world: &'a World, idx: Self::Index) -> BorrowResult<Self::Item<'a>> {
+        Ok(KeyIndex {
+            storage: world.all_storages.components.borrow_ref(idx)?,
+        })
+    }
+
+    #[inline]
+    fn access(access: &mut Access) -> Result<(), AccessConflict> {
+        access.read::<ComponentStorage<C>>(std::any::type_name::<C>())
+    }
+}
@@ -0,0 +1,259 @@
+use crate::prelude::*;
+
+/// The components an entity must have, or must not have, to be part of a
+/// join.
+///
+/// Filters are bitsets indexed by component id, so they are checked against
+/// each entity's component signature without looking in any storage.
+#[derive(Debug, Default, Clone)]
+pub struct JoinFilter {
+    with: Vec<u64>,
+    without: Vec<u64>,
+}
+
+impl JoinFilter {
+    /// Require entities to have a component.
+    pub fn with(&mut self, id: ComponentId) {
+        set_bit(&mut self.with, id);
+    }
+
+    /// Require entities not to have a component.
+    pub fn without(&mut self, id: ComponentId) {
+        set_bit(&mut self.without, id);
+    }
+
+    /// Whether entities are required to have a component.
+    #[inline]
+    pub fn requires(&self, id: ComponentId) -> bool {
+        self.with
+            .get(id.index() / 64)
+            .is_some_and(|word| word & (1 << (id.index() % 64)) != 0)
+    }
+
+    /// Check an entity's signature against the filter.
+    #[inline]
+    pub(crate) fn matches(&self, mut signature: impl FnMut(usize) -> u64) -> bool {
+        self.with
+            .iter()
+            .enumerate()
+            .all(|(word, &with)| signature(word) & with == with)
+            && self
+                .without
+                .iter()
+                .enumerate()
+                .all(|(word, &without)| signature(word) & without == 0)
+    }
+}
+
+fn set_bit(words: &mut Vec<u64>, id: ComponentId) {
+    let (word, bit) = (id.index() / 64, id.index() % 64);
+    if words.len() <= word {
+        words.resize(word + 1, 0);
+    }
+    words[word] |= 1 << bit;
+}
+
+/// One of the things being joined, such as `&Comp<C>` or `&Without<C>`.
+pub trait JoinParam: for<'c> JoinFetch<'c> {
+    /// Add the components this requires or excludes to `filter`.
+    fn filter(&self, filter: &mut JoinFilter);
+
+    /// The number of entities with this component, if the join can iterate
+    /// over this param's storage.
+    fn driver_len(&self) -> Option<usize>;
+
+    /// Get the index of the next entity in this param's storage, starting
+    /// from `cursor` (which starts at 0).
+    fn next_index(&self, cursor: &mut usize) -> Option<usize>;
+
+    /// Check if the entity at `index` passes the filter.
+    fn matches(&self, index: usize, filter: &JoinFilter) -> bool;
+
+    fn entity_id(&self, index: usize) -> EntityId;
+}
+
+/// Fetching the data for an entity in a join, borrowed for `'c`.
+///
+/// This is separate from [`JoinParam`] so that closures can take items
+/// borrowed for any lifetime.
+pub trait JoinFetch<'c> {
+    type Item;
+
+    /// Fetch the data for an entity that passes the filter.
+    fn fetch(&'c mut self, index: usize) -> Self::Item;
+}
+
+/// A tuple of [`JoinParam`]s, which can be iterated over together.
+///
+/// Only entities that have every component being joined (and pass any
+/// [`With`] and [`Without`] filters) are visited. The smallest storage is
+/// iterated over, and other entities are filtered using each entity's
+/// component signature.
+///
+/// Joins use internal iteration, as borrowing components mutably from
+/// several storages at once can't be expressed with [`Iterator`].
+pub trait Join: for<'c> JoinFetch<'c> + Sized {
+    #[doc(hidden)]
+    fn filter(&self, filter: &mut JoinFilter);
+
+    /// The param with the fewest entities, and its number of entities.
+    #[doc(hidden)]
+    fn driver(&self) -> Option<(usize, usize)>;
+
+    #[doc(hidden)]
+    fn next_index(&self, param: usize, cursor: &mut usize) -> Option<usize>;
+
+    #[doc(hidden)]
+    fn matches(&self, index: usize, filter: &JoinFilter) -> bool;
+
+    #[doc(hidden)]
+    fn entity_id(&self, index: usize) -> EntityId;
+
+    /// Join the params.
+    ///
+    /// Disabled entities are skipped, unless the join asks for them with
+    /// [`With<Disabled>`].
+    fn join(self) -> Joined<Self> {
+        let mut filter = JoinFilter::default();
+        self.filter(&mut filter);
+
+        if !filter.requires(Disabled::ID) {
+            filter.without(Disabled::ID);
+        }
+
+        Joined {
+            params: self,
+            filter,
+        }
+    }
+}
+
+/// Params that are ready to be iterated over together.
+pub struct Joined<J: Join> {
+    params: J,
+    filter: JoinFilter,
+}
+
+impl<J: Join> Joined<J> {
+    /// Visit disabled entities as well as enabled ones.
+    pub fn include_disabled(mut self) -> Self {
+        self.filter = JoinFilter::default();
+        self.params.filter(&mut self.filter);
+        self
+    }
+
+    /// Call `f` with the components of each entity in the join.
+    ///
+    /// Panics if no component storages are being joined.
+    pub fn for_each(self, mut f: impl for<'c> FnMut(<J as JoinFetch<'c>>::Item)) {
+        self.for_each_with_id(|_, item| f(item));
+    }
+
+    /// Call `f` with each entity in the join, and its components.
+    ///
+    /// Panics if no component storages are being joined.
+    pub fn for_each_with_id(
+        mut self,
+        mut f: impl for<'c> FnMut(EntityId, <J as JoinFetch<'c>>::Item),
+    ) {
+        let Some((driver, _)) = self.params.driver() else {
+            panic!("a join needs at least one component storage");
+        };
+
+        let mut cursor = 0;
+        while let Some(index) = self.params.next_index(driver, &mut cursor) {
+            if self.params.matches(index, &self.filter) {
+                let entity = self.params.entity_id(index);
+                f(entity, self.params.fetch(index));
+            }
+        }
+    }
+
+    /// Count the entities in the join.
+    pub fn count(self) -> usize {
+        let mut count = 0;
+        self.for_each(|_| count += 1);
+        count
+    }
+}
+
+macro_rules! impl_join {
+    ($first:ident $(, $param:ident)*) => {
+        impl<$first: JoinParam $(, $param: JoinParam)*> Join for ($first, $($param,)*) {
+            #[inline]
+            #[allow(non_snake_case)]
+            fn filter(&self, filter: &mut JoinFilter) {
+                let ($first, $($param,)*) = self;
+                $first.filter(filter);
+                $($param.filter(filter);)*
+            }
+
+            #[allow(non_snake_case, unused_mut, unused_assignments)]
+            fn driver(&self) -> Option<(usize, usize)> {
+                let ($first, $($param,)*) = self;
+
+                let mut driver: Option<(usize, usize)> = None;
+                let mut param = 0;
+                for len in [$first.driver_len(), $($param.driver_len(),)*] {
+                    if let Some(len) = len {
+                        if driver.is_none_or(|(_, min)| len < min) {
+                            driver = Some((param, len));
+                        }
+                    }
+                    param += 1;
+                }
+                driver
+            }
+
+            #[allow(non_snake_case, unused_variables, unused_mut, unused_assignments)]
+            fn next_index(&self, param: usize, cursor: &mut usize) -> Option<usize> {
+                let ($first, $($param,)*) = self;
+
+                if param == 0 {
+                    return $first.next_index(cursor);
+                }
+                let mut current = 0;
+                $(
+                    current += 1;
+                    if param == current {
+                        return $param.next_index(cursor);
+                    }
+                )*
+                unreachable!()
+            }
+
+            #[inline]
+            fn matches(&self, index: usize, filter: &JoinFilter) -> bool {
+                self.0.matches(index, filter)
+            }
+
+            #[inline]
+            fn entity_id(&self, index: usize) -> EntityId {
+                self.0.entity_id(index)
+            }
+
+        }
+
+        impl<'c, $first: JoinFetch<'c> $(, $param: JoinFetch<'c>)*> JoinFetch<'c>
+            for ($first, $($param,)*)
+        {
+            type Item = ($first::Item, $($param::Item,)*);
+
+            #[inline]
+            #[allow(non_snake_case)]
+            fn fetch(&'c mut self, index: usize) -> Self::Item {
+                let ($first, $($param,)*) = self;
+                ($first.fetch(index), $($param.fetch(index),)*)
+            }
+        }
+    };
+}
+
+impl_join!(J0);
+impl_join!(J0, J1);
+impl_join!(J0, J1, J2);
+impl_join!(J0, J1, J2, J3);
+impl_join!(J0, J1, J2, J3, J4);
+impl_join!(J0, J1, J2, J3, J4, J5);
+impl_join!(J0, J1, J2, J3, J4, J5, J6);
+impl_join!(J0, J1, J2, J3, J4, J5, J6, J7);
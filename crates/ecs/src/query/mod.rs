@@ -1,5 +1,17 @@
 mod component;
+mod entities;
+mod filter;
+mod index;
+mod join;
+#[cfg(feature = "parallel")]
+mod parallel;
 mod unique;
 
 pub use self::component::{Comp, CompMut};
-pub use self::unique::{Unique, UniqueMut};
+pub use self::entities::Entities;
+pub use self::filter::{With, Without};
+pub use self::index::KeyIndex;
+pub use self::join::{Join, JoinFetch, JoinFilter, JoinParam, Joined};
+#[cfg(feature = "parallel")]
+pub use self::parallel::{ParJoin, DEFAULT_MIN_BATCH_SIZE};
+pub use self::unique::{NonSend, NonSendMut, Unique, UniqueMut};
@@ -0,0 +1,106 @@
+use rayon::prelude::*;
+
+use crate::prelude::*;
+
+/// The default for [`ParJoin::min_batch_size`].
+pub const DEFAULT_MIN_BATCH_SIZE: usize = 256;
+
+impl<'a, C: Component> Comp<'a, C> {
+    /// Iterate over the components in parallel.
+    ///
+    /// Use [`IndexedParallelIterator::with_min_len`] to set the minimum
+    /// number of components each thread processes at once.
+    #[inline]
+    pub fn par_iter(&self) -> rayon::slice::Iter<'_, C> {
+        self.as_slice().par_iter()
+    }
+}
+
+impl<'a, C: Component> CompMut<'a, C> {
+    #[inline]
+    pub fn par_iter(&self) -> rayon::slice::Iter<'_, C> {
+        self.as_slice().par_iter()
+    }
+
+    /// Iterate over the components mutably in parallel.
+    ///
+    /// Use [`IndexedParallelIterator::with_min_len`] to set the minimum
+    /// number of components each thread processes at once.
+    #[inline]
+    pub fn par_iter_mut(&mut self) -> rayon::slice::IterMut<'_, C> {
+        self.as_mut_slice().par_iter_mut()
+    }
+
+    /// Join this storage with read-only params, such as `(&Comp<C>,)`, and
+    /// process the entities in parallel.
+    ///
+    /// Unlike [`Join::join`], this storage is always the one iterated over,
+    /// so it should be the smallest.
+    pub fn par_join<J: Join + Copy + Send + Sync>(&mut self, others: J) -> ParJoin<'_, 'a, C, J> {
+        let mut filter = JoinFilter::default();
+        filter.with(self.storage.id());
+        others.filter(&mut filter);
+
+        if !filter.requires(Disabled::ID) {
+            filter.without(Disabled::ID);
+        }
+
+        ParJoin {
+            storage: self,
+            others,
+            filter,
+            min_batch_size: DEFAULT_MIN_BATCH_SIZE,
+        }
+    }
+}
+
+/// A storage joined with read-only params, from [`CompMut::par_join`].
+pub struct ParJoin<'b, 'a, C: Component, J: Join> {
+    storage: &'b mut CompMut<'a, C>,
+    others: J,
+    filter: JoinFilter,
+    min_batch_size: usize,
+}
+
+impl<'b, 'a, C: Component, J: Join + Copy + Send + Sync> ParJoin<'b, 'a, C, J> {
+    /// Set the minimum number of entities each thread processes at once.
+    ///
+    /// Panics if `min_batch_size` is 0.
+    pub fn min_batch_size(mut self, min_batch_size: usize) -> Self {
+        assert!(min_batch_size > 0, "batches can't be empty");
+        self.min_batch_size = min_batch_size;
+        self
+    }
+
+    /// Visit disabled entities as well as enabled ones.
+    pub fn include_disabled(mut self) -> Self {
+        self.filter = JoinFilter::default();
+        self.filter.with(self.storage.storage.id());
+        self.others.filter(&mut self.filter);
+        self
+    }
+
+    /// Call `f` with the components of each entity in the join, on a pool of
+    /// threads.
+    pub fn for_each(self, f: impl for<'c> Fn(&mut C, <J as JoinFetch<'c>>::Item) + Send + Sync) {
+        let filter = self.filter;
+        let mut cursor = 0;
+        let indices: Vec<_> =
+            std::iter::from_fn(|| self.storage.storage.next_index(&mut cursor)).collect();
+
+        let entities = self.storage.entities;
+        let others = self.others;
+        let components = self.storage.storage.as_mut_slice();
+
+        components
+            .par_iter_mut()
+            .zip(&indices)
+            .with_min_len(self.min_batch_size)
+            .for_each(|(component, &index)| {
+                if filter.matches(|word| entities.signature_word(index, word)) {
+                    let mut others = others;
+                    f(component, others.fetch(index));
+                }
+            });
+    }
+}
@@ -1,89 +1,232 @@
 use std::any::Any;
-use std::cell::{Ref, RefMut};
 use std::ops::{Deref, DerefMut};
 
 use crate::all_storages::erased::StorageIdx;
+use crate::all_storages::non_send::NonSendStorage;
 use crate::prelude::*;
 use crate::storage::unique::UniqueStorage;
 
-pub struct Unique<'a, T: Any> {
-    storage: Ref<'a, UniqueStorage<T>>,
-}
-
-pub struct UniqueMut<'a, T: Any> {
-    storage: RefMut<'a, UniqueStorage<T>>,
-}
-
-impl<'a, T: Any> Unique<'a, T> {
-    #[inline]
-    pub fn get(&self) -> &T {
-        &self.storage.0
+/// Treat a missing unique as `None`, while still reporting borrow conflicts.
+fn optional<T>(result: BorrowResult<T>) -> BorrowResult<Option<T>> {
+    match result {
+        Ok(unique) => Ok(Some(unique)),
+        Err(BorrowError::StorageNotFound { .. } | BorrowError::ResourceNotFound { .. }) => Ok(None),
+        Err(err) => Err(err),
     }
 }
 
-impl<'a, T: Any> UniqueMut<'a, T> {
-    #[inline]
-    pub fn get(&self) -> &T {
-        &self.storage.0
-    }
-
-    #[inline]
-    pub fn get_mut(&mut self) -> &mut T {
-        &mut self.storage.0
-    }
-}
-
-impl<'a, T: Any> Deref for Unique<'a, T> {
-    type Target = T;
-
-    #[inline]
-    fn deref(&self) -> &Self::Target {
-        &self.storage.0
-    }
-}
-
-impl<'a, T: Any> Deref for UniqueMut<'a, T> {
-    type Target = T;
-
-    #[inline]
-    fn deref(&self) -> &Self::Target {
-        &self.storage.0
-    }
+// Uniques and non-send uniques only differ in the bounds on their type, and
+// where and how they are stored.
+macro_rules! impl_unique {
+    (
+        $(#[$unique_doc:meta])*
+        $unique:ident,
+        $(#[$unique_mut_doc:meta])*
+        $unique_mut:ident,
+        [$($bounds:tt)+],
+        $storage:ident<T>,
+        $storages:ident,
+        is_send: $is_send:expr,
+        $as_ref:ident,
+        $as_mut:ident $(,)?
+    ) => {
+        $(#[$unique_doc])*
+        pub struct $unique<'a, T: $($bounds)+> {
+            unique: Ref<'a, T>,
+        }
+
+        $(#[$unique_mut_doc])*
+        pub struct $unique_mut<'a, T: $($bounds)+> {
+            unique: RefMut<'a, T>,
+        }
+
+        impl<'a, T: $($bounds)+> $unique<'a, T> {
+            #[inline]
+            pub fn get(&self) -> &T {
+                &self.unique
+            }
+        }
+
+        impl<'a, T: $($bounds)+> $unique_mut<'a, T> {
+            #[inline]
+            pub fn get(&self) -> &T {
+                &self.unique
+            }
+
+            #[inline]
+            pub fn get_mut(&mut self) -> &mut T {
+                &mut self.unique
+            }
+        }
+
+        impl<'a, T: $($bounds)+> Deref for $unique<'a, T> {
+            type Target = T;
+
+            #[inline]
+            fn deref(&self) -> &Self::Target {
+                &self.unique
+            }
+        }
+
+        impl<'a, T: $($bounds)+> Deref for $unique_mut<'a, T> {
+            type Target = T;
+
+            #[inline]
+            fn deref(&self) -> &Self::Target {
+                &self.unique
+            }
+        }
+
+        impl<'a, T: $($bounds)+> DerefMut for $unique_mut<'a, T> {
+            #[inline]
+            fn deref_mut(&mut self) -> &mut Self::Target {
+                &mut self.unique
+            }
+        }
+
+        impl<'q, T: $($bounds)+> Query for $unique<'q, T> {
+            type Index = StorageIdx<$storage<T>>;
+            type Item<'a> = $unique<'a, T>;
+
+            #[inline]
+            fn is_send() -> bool {
+                $is_send
+            }
+
+            fn lookup(world: &mut World) -> Self::Index {
+                world.all_storages.$storages.lookup_or_insert()
+            }
+
+            #[inline]
+            #[cfg_attr(debug_assertions, track_caller)]
+            fn borrow<'a>(world: &'a World, idx: Self::Index) -> BorrowResult<Self::Item<'a>> {
+                let storage = world.all_storages.$storages.borrow_ref(idx)?;
+                let unique = Ref::try_map(storage, |storage| storage.0.$as_ref()).map_err(|_| {
+                    BorrowError::ResourceNotFound {
+                        type_name: std::any::type_name::<T>(),
+                    }
+                })?;
+                Ok($unique { unique })
+            }
+
+            #[inline]
+            fn access(access: &mut Access) -> Result<(), AccessConflict> {
+                access.read::<$storage<T>>(std::any::type_name::<T>())
+            }
+        }
+
+        impl<'q, T: $($bounds)+> Query for $unique_mut<'q, T> {
+            type Index = StorageIdx<$storage<T>>;
+            type Item<'a> = $unique_mut<'a, T>;
+
+            #[inline]
+            fn is_send() -> bool {
+                $is_send
+            }
+
+            fn lookup(world: &mut World) -> Self::Index {
+                world.all_storages.$storages.lookup_or_insert()
+            }
+
+            #[inline]
+            #[cfg_attr(debug_assertions, track_caller)]
+            fn borrow<'a>(world: &'a World, idx: Self::Index) -> BorrowResult<Self::Item<'a>> {
+                let storage = world.all_storages.$storages.borrow_mut(idx)?;
+                let unique =
+                    RefMut::try_map(storage, |storage| storage.0.$as_mut()).map_err(|_| {
+                        BorrowError::ResourceNotFound {
+                            type_name: std::any::type_name::<T>(),
+                        }
+                    })?;
+                Ok($unique_mut { unique })
+            }
+
+            #[inline]
+            fn access(access: &mut Access) -> Result<(), AccessConflict> {
+                access.write::<$storage<T>>(std::any::type_name::<T>())
+            }
+        }
+
+        impl<'q, T: $($bounds)+> Query for Option<$unique<'q, T>> {
+            type Index = StorageIdx<$storage<T>>;
+            type Item<'a> = Option<$unique<'a, T>>;
+
+            #[inline]
+            fn is_send() -> bool {
+                $is_send
+            }
+
+            fn lookup(world: &mut World) -> Self::Index {
+                $unique::<T>::lookup(world)
+            }
+
+            #[inline]
+            #[cfg_attr(debug_assertions, track_caller)]
+            fn borrow<'a>(world: &'a World, idx: Self::Index) -> BorrowResult<Self::Item<'a>> {
+                optional($unique::<T>::borrow(world, idx))
+            }
+
+            #[inline]
+            fn access(access: &mut Access) -> Result<(), AccessConflict> {
+                $unique::<T>::access(access)
+            }
+        }
+
+        impl<'q, T: $($bounds)+> Query for Option<$unique_mut<'q, T>> {
+            type Index = StorageIdx<$storage<T>>;
+            type Item<'a> = Option<$unique_mut<'a, T>>;
+
+            #[inline]
+            fn is_send() -> bool {
+                $is_send
+            }
+
+            fn lookup(world: &mut World) -> Self::Index {
+                $unique_mut::<T>::lookup(world)
+            }
+
+            #[inline]
+            #[cfg_attr(debug_assertions, track_caller)]
+            fn borrow<'a>(world: &'a World, idx: Self::Index) -> BorrowResult<Self::Item<'a>> {
+                optional($unique_mut::<T>::borrow(world, idx))
+            }
+
+            #[inline]
+            fn access(access: &mut Access) -> Result<(), AccessConflict> {
+                $unique_mut::<T>::access(access)
+            }
+        }
+    };
 }
 
-impl<'a, T: Any> DerefMut for UniqueMut<'a, T> {
-    #[inline]
-    fn deref_mut(&mut self) -> &mut Self::Target {
-        &mut self.storage.0
-    }
-}
-
-impl<'a, T: Any> Query<'a> for Unique<'a, T> {
-    type Index = BorrowResult<StorageIdx<UniqueStorage<T>>>;
-
-    fn lookup(world: &mut World) -> Self::Index {
-        world.all_storages.uniques.lookup()
-    }
-
-    #[inline]
-    fn borrow(world: &'a World, idx: Self::Index) -> BorrowResult<Self> {
-        Ok(Self {
-            storage: world.all_storages.uniques.borrow_ref(idx?)?,
-        })
-    }
-}
-
-impl<'a, T: Any> Query<'a> for UniqueMut<'a, T> {
-    type Index = BorrowResult<StorageIdx<UniqueStorage<T>>>;
-
-    fn lookup(world: &mut World) -> Self::Index {
-        world.all_storages.uniques.lookup()
-    }
-
-    #[inline]
-    fn borrow(world: &'a World, idx: Self::Index) -> BorrowResult<Self> {
-        Ok(Self {
-            storage: world.all_storages.uniques.borrow_mut(idx?)?,
-        })
-    }
-}
+impl_unique!(
+    /// Shared access to a unique.
+    Unique,
+    /// Exclusive access to a unique.
+    UniqueMut,
+    [Any + Send + Sync],
+    UniqueStorage<T>,
+    uniques,
+    is_send: true,
+    as_ref,
+    as_mut,
+);
+
+impl_unique!(
+    /// Shared access to a unique that isn't `Send` or `Sync`.
+    ///
+    /// Borrowing fails with [`BorrowError::WrongThread`] on any thread other
+    /// than the world's, and systems using it aren't `Send`.
+    NonSend,
+    /// Exclusive access to a unique that isn't `Send` or `Sync`.
+    ///
+    /// Borrowing fails with [`BorrowError::WrongThread`] on any thread other
+    /// than the world's, and systems using it aren't `Send`.
+    NonSendMut,
+    [Any],
+    NonSendStorage<T>,
+    non_send,
+    is_send: false,
+    as_deref,
+    as_deref_mut,
+);
@@ -0,0 +1,374 @@
+use std::fmt::Debug;
+use std::marker::PhantomData;
+
+use crate::prelude::*;
+
+/// A system in a schedule that failed.
+#[derive(Debug)]
+pub struct SystemFailure {
+    pub system: &'static str,
+    pub error: SystemError<Box<dyn Debug>>,
+}
+
+/// What a schedule does when one of its systems fails.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum ErrorPolicy {
+    /// Stop running the schedule and return the failure.
+    #[default]
+    Abort,
+
+    /// Panic with the failure.
+    Panic,
+
+    /// Pass the failure to the schedule's logger (see
+    /// [`Schedule::log_with`]) and carry on running the schedule.
+    LogAndContinue,
+
+    /// Skip any systems that depend on the failed system, and carry on
+    /// running the rest of the schedule.
+    SkipDependants,
+
+    /// Skip any systems that depend on the failed system, and run it again at
+    /// the start of the next run, for systems that may not be ready straight
+    /// away.
+    ///
+    /// The failure is only reported if the retry fails too. If the retry
+    /// succeeds, the system still runs in its usual place afterwards.
+    RetryNextFrame,
+}
+
+/// Identifies a system in a schedule.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct SystemId(usize);
+
+/// How a system is run by a schedule.
+#[derive(Debug, Default, Clone)]
+pub struct SystemConfig {
+    on_error: Option<ErrorPolicy>,
+    after: Vec<SystemId>,
+}
+
+impl SystemConfig {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Override the schedule's error policy for this system.
+    pub fn on_error(mut self, policy: ErrorPolicy) -> Self {
+        self.on_error = Some(policy);
+        self
+    }
+
+    /// Make this system depend on another, so that it is skipped if the other
+    /// system fails with [`ErrorPolicy::SkipDependants`] or
+    /// [`ErrorPolicy::RetryNextFrame`].
+    pub fn after(mut self, system: SystemId) -> Self {
+        self.after.push(system);
+        self
+    }
+}
+
+/// What happened to the systems in a schedule during one run.
+#[derive(Debug, Default)]
+pub struct ScheduleReport {
+    /// Systems that failed.
+    pub failures: Vec<SystemFailure>,
+
+    /// Systems that were skipped because a system they depend on failed.
+    pub skipped: Vec<&'static str>,
+
+    /// Systems that failed under [`ErrorPolicy::RetryNextFrame`], and will
+    /// be retried at the start of the next run.
+    pub retrying: Vec<&'static str>,
+}
+
+impl ScheduleReport {
+    /// Whether every system ran successfully.
+    #[inline]
+    pub fn is_ok(&self) -> bool {
+        self.failures.is_empty() && self.skipped.is_empty() && self.retrying.is_empty()
+    }
+}
+
+/// Two systems with conflicting access and no ordering between them.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Ambiguity {
+    pub systems: [&'static str; 2],
+
+    /// The names of the types both systems borrow, at least one mutably.
+    pub conflicts: Vec<&'static str>,
+}
+
+trait ScheduledSystem {
+    fn run(&mut self, world: &mut World) -> SystemResult<(), Box<dyn Debug>>;
+    fn name(&self) -> &'static str;
+}
+
+struct SystemWrapper<S, Params, Output> {
+    system: S,
+    phantom_data: PhantomData<fn() -> (Params, Output)>,
+}
+
+impl<S, Params, Output> ScheduledSystem for SystemWrapper<S, Params, Output>
+where
+    S: System<Params, Output>,
+    Output: SystemOutput,
+    Output::Error: Debug + 'static,
+{
+    fn run(&mut self, world: &mut World) -> SystemResult<(), Box<dyn Debug>> {
+        self.system
+            .run(world)
+            .map(|_| ())
+            .map_err(|err| err.map(|err| Box::new(err) as Box<dyn Debug>))
+    }
+
+    fn name(&self) -> &'static str {
+        self.system.name()
+    }
+}
+
+struct ScheduleEntry {
+    system: Box<dyn ScheduledSystem>,
+    config: SystemConfig,
+    access: Access,
+
+    /// Whether the system failed under [`ErrorPolicy::RetryNextFrame`], and
+    /// has to be retried at the start of the next run.
+    retry: bool,
+}
+
+type Logger = Box<dyn FnMut(&SystemFailure)>;
+
+/// A list of systems to run on a world.
+///
+/// Systems (including exclusive systems) run one at a time in the order they
+/// were added, on the thread that runs the schedule. Systems using non-send
+/// uniques fail with [`BorrowError::WrongThread`] unless that is the thread
+/// the world was created on.
+#[derive(Default)]
+pub struct Schedule {
+    systems: Vec<ScheduleEntry>,
+    on_error: ErrorPolicy,
+    logger: Option<Logger>,
+
+    /// The system that aborted the last run, and the systems whose
+    /// dependants were being skipped, for [`Schedule::resume`].
+    aborted: Option<(usize, Vec<bool>)>,
+}
+
+impl Schedule {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the error policy for systems that don't override it.
+    pub fn on_error(&mut self, policy: ErrorPolicy) -> &mut Self {
+        self.on_error = policy;
+        self
+    }
+
+    /// Set where failures are logged under [`ErrorPolicy::LogAndContinue`],
+    /// such as a logging library. Without a logger, failures are only
+    /// recorded in the [`ScheduleReport`].
+    pub fn log_with(&mut self, logger: impl FnMut(&SystemFailure) + 'static) -> &mut Self {
+        self.logger = Some(Box::new(logger));
+        self
+    }
+
+    /// Add a system to the end of the schedule.
+    ///
+    /// Panics if the system's parameters conflict with each other.
+    pub fn add_system<S, Params, Output>(&mut self, system: S) -> SystemId
+    where
+        S: System<Params, Output> + 'static,
+        Params: 'static,
+        Output: SystemOutput + 'static,
+        Output::Error: Debug + 'static,
+    {
+        self.add_system_with(system, SystemConfig::default())
+    }
+
+    /// Add a system to the end of the schedule with the given configuration.
+    ///
+    /// Panics if the system's parameters conflict with each other, or the
+    /// system depends on a system that isn't in this schedule.
+    pub fn add_system_with<S, Params, Output>(
+        &mut self,
+        system: S,
+        config: SystemConfig,
+    ) -> SystemId
+    where
+        S: System<Params, Output> + 'static,
+        Params: 'static,
+        Output: SystemOutput + 'static,
+        Output::Error: Debug + 'static,
+    {
+        let id = SystemId(self.systems.len());
+
+        let access = system
+            .access()
+            .unwrap_or_else(|conflict| panic!("system {}: {conflict}", system.name()));
+
+        // Dependencies are always added first, so running systems in order
+        // runs them before their dependants.
+        for dependency in &config.after {
+            assert!(
+                dependency.0 < id.0,
+                "system {dependency:?} not in this schedule"
+            );
+        }
+
+        self.systems.push(ScheduleEntry {
+            system: Box::new(SystemWrapper {
+                system,
+                phantom_data: PhantomData,
+            }),
+            config,
+            access,
+            retry: false,
+        });
+
+        id
+    }
+
+    /// Run all systems, handling failures according to their error policies.
+    ///
+    /// The world is flushed before each system runs. Returns an error if a
+    /// system fails with [`ErrorPolicy::Abort`].
+    pub fn run(&mut self, world: &mut World) -> Result<ScheduleReport, SystemFailure> {
+        self.aborted = None;
+        self.resume(world)
+    }
+
+    /// Run the systems from the one that aborted the last run, or all systems
+    /// if the last run wasn't aborted.
+    ///
+    /// Systems that ran before the abort aren't run again, which suits
+    /// schedules that only need to succeed once, like startup schedules.
+    pub fn resume(&mut self, world: &mut World) -> Result<ScheduleReport, SystemFailure> {
+        let mut report = ScheduleReport::default();
+
+        // Systems whose dependants should be skipped this frame.
+        let (start, mut failed) = self.aborted.take().unwrap_or_default();
+        failed.resize(self.systems.len(), false);
+
+        // Retry the systems that failed last run before anything else runs.
+        for (idx, entry) in self.systems.iter_mut().enumerate() {
+            if !std::mem::take(&mut entry.retry) {
+                continue;
+            }
+
+            world.flush();
+            if let Err(error) = entry.system.run(world) {
+                failed[idx] = true;
+                report.failures.push(SystemFailure {
+                    system: entry.system.name(),
+                    error,
+                });
+            }
+        }
+
+        for (idx, entry) in self.systems.iter_mut().enumerate().skip(start) {
+            let name = entry.system.name();
+
+            // The system's retry failed, so it was already reported.
+            if failed[idx] {
+                continue;
+            }
+
+            if entry
+                .config
+                .after
+                .iter()
+                .any(|dependency| failed[dependency.0])
+            {
+                failed[idx] = true;
+                report.skipped.push(name);
+                continue;
+            }
+
+            world.flush();
+
+            let Err(error) = entry.system.run(world) else {
+                continue;
+            };
+
+            let failure = SystemFailure {
+                system: name,
+                error,
+            };
+
+            match entry.config.on_error.unwrap_or(self.on_error) {
+                ErrorPolicy::Abort => {
+                    self.aborted = Some((idx, failed));
+                    return Err(failure);
+                }
+                ErrorPolicy::Panic => panic!("system {name} failed: {:?}", failure.error),
+                ErrorPolicy::LogAndContinue => {
+                    if let Some(logger) = &mut self.logger {
+                        logger(&failure);
+                    }
+                    report.failures.push(failure);
+                }
+                ErrorPolicy::SkipDependants => {
+                    failed[idx] = true;
+                    report.failures.push(failure);
+                }
+                ErrorPolicy::RetryNextFrame => {
+                    failed[idx] = true;
+                    entry.retry = true;
+                    report.retrying.push(name);
+                }
+            }
+        }
+
+        Ok(report)
+    }
+
+    /// Find pairs of systems whose access conflicts, where neither is ordered
+    /// after the other.
+    pub fn ambiguities(&self) -> Vec<Ambiguity> {
+        // Every system each system is (transitively) ordered after.
+        let mut ancestors: Vec<Vec<bool>> = Vec::with_capacity(self.systems.len());
+        for (idx, entry) in self.systems.iter().enumerate() {
+            let mut system_ancestors = vec![false; idx];
+            for dependency in &entry.config.after {
+                system_ancestors[dependency.0] = true;
+                for (ancestor, &is_ancestor) in ancestors[dependency.0].iter().enumerate() {
+                    system_ancestors[ancestor] |= is_ancestor;
+                }
+            }
+            ancestors.push(system_ancestors);
+        }
+
+        let mut ambiguities = vec![];
+
+        for (b, entry_b) in self.systems.iter().enumerate() {
+            for (a, entry_a) in self.systems[..b].iter().enumerate() {
+                if ancestors[b][a] {
+                    continue;
+                }
+
+                let conflicts = entry_a.access.conflicts(&entry_b.access);
+                if !conflicts.is_empty() {
+                    ambiguities.push(Ambiguity {
+                        systems: [entry_a.system.name(), entry_b.system.name()],
+                        conflicts,
+                    });
+                }
+            }
+        }
+
+        ambiguities
+    }
+
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.systems.len()
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.systems.is_empty()
+    }
+}
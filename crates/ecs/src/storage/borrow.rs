@@ -0,0 +1,176 @@
+use std::fmt;
+#[cfg(not(debug_assertions))]
+use std::marker::PhantomData;
+use std::ops::{Deref, DerefMut};
+use std::panic::Location;
+
+use parking_lot::{MappedRwLockReadGuard, MappedRwLockWriteGuard};
+
+/// A shared borrow of a storage, or part of one.
+pub struct Ref<'a, T: ?Sized> {
+    value: MappedRwLockReadGuard<'a, T>,
+    record: BorrowRecord<'a>,
+}
+
+impl<'a, T: ?Sized> Ref<'a, T> {
+    #[inline]
+    pub(crate) fn new(value: MappedRwLockReadGuard<'a, T>, record: BorrowRecord<'a>) -> Self {
+        Self { value, record }
+    }
+
+    #[inline]
+    pub fn map<U: ?Sized>(this: Self, f: impl FnOnce(&T) -> &U) -> Ref<'a, U> {
+        Ref {
+            value: MappedRwLockReadGuard::map(this.value, f),
+            record: this.record,
+        }
+    }
+
+    /// Borrow part of the data, or return the original borrow if `f` fails.
+    #[inline]
+    pub fn try_map<U: ?Sized>(
+        this: Self,
+        f: impl FnOnce(&T) -> Option<&U>,
+    ) -> Result<Ref<'a, U>, Self> {
+        let record = this.record;
+        match MappedRwLockReadGuard::try_map(this.value, f) {
+            Ok(value) => Ok(Ref { value, record }),
+            Err(value) => Err(Ref { value, record }),
+        }
+    }
+}
+
+impl<T: ?Sized> Deref for Ref<'_, T> {
+    type Target = T;
+
+    #[inline]
+    fn deref(&self) -> &T {
+        &self.value
+    }
+}
+
+impl<T: ?Sized + fmt::Debug> fmt::Debug for Ref<'_, T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        (**self).fmt(f)
+    }
+}
+
+/// An exclusive borrow of a storage, or part of one.
+pub struct RefMut<'a, T: ?Sized> {
+    value: MappedRwLockWriteGuard<'a, T>,
+    record: BorrowRecord<'a>,
+}
+
+impl<'a, T: ?Sized> RefMut<'a, T> {
+    #[inline]
+    pub(crate) fn new(value: MappedRwLockWriteGuard<'a, T>, record: BorrowRecord<'a>) -> Self {
+        Self { value, record }
+    }
+
+    #[inline]
+    pub fn map<U: ?Sized>(this: Self, f: impl FnOnce(&mut T) -> &mut U) -> RefMut<'a, U> {
+        RefMut {
+            value: MappedRwLockWriteGuard::map(this.value, f),
+            record: this.record,
+        }
+    }
+
+    /// Borrow part of the data, or return the original borrow if `f` fails.
+    #[inline]
+    pub fn try_map<U: ?Sized>(
+        this: Self,
+        f: impl FnOnce(&mut T) -> Option<&mut U>,
+    ) -> Result<RefMut<'a, U>, Self> {
+        let record = this.record;
+        match MappedRwLockWriteGuard::try_map(this.value, f) {
+            Ok(value) => Ok(RefMut { value, record }),
+            Err(value) => Err(RefMut { value, record }),
+        }
+    }
+}
+
+impl<T: ?Sized> Deref for RefMut<'_, T> {
+    type Target = T;
+
+    #[inline]
+    fn deref(&self) -> &T {
+        &self.value
+    }
+}
+
+impl<T: ?Sized> DerefMut for RefMut<'_, T> {
+    #[inline]
+    fn deref_mut(&mut self) -> &mut T {
+        &mut self.value
+    }
+}
+
+impl<T: ?Sized + fmt::Debug> fmt::Debug for RefMut<'_, T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        (**self).fmt(f)
+    }
+}
+
+/// Where a storage is currently borrowed, to explain borrow errors.
+///
+/// Only tracked in debug builds.
+#[derive(Default)]
+pub(crate) struct Borrowers {
+    #[cfg(debug_assertions)]
+    locations: parking_lot::Mutex<Vec<&'static Location<'static>>>,
+}
+
+impl Borrowers {
+    /// Record a borrow made by the caller, until the record is dropped.
+    #[cfg_attr(debug_assertions, track_caller)]
+    #[inline]
+    pub(crate) fn record(&self) -> BorrowRecord<'_> {
+        #[cfg(debug_assertions)]
+        {
+            let location = Location::caller();
+            self.locations.lock().push(location);
+            BorrowRecord {
+                borrowers: self,
+                location,
+            }
+        }
+
+        #[cfg(not(debug_assertions))]
+        BorrowRecord {
+            phantom_data: PhantomData,
+        }
+    }
+
+    /// The most recent borrow that is still held.
+    #[inline]
+    pub(crate) fn last(&self) -> Option<&'static Location<'static>> {
+        #[cfg(debug_assertions)]
+        {
+            self.locations.lock().last().copied()
+        }
+
+        #[cfg(not(debug_assertions))]
+        None
+    }
+}
+
+/// A borrow recorded in [`Borrowers`], which is forgotten when dropped.
+pub(crate) struct BorrowRecord<'a> {
+    #[cfg(debug_assertions)]
+    borrowers: &'a Borrowers,
+    #[cfg(debug_assertions)]
+    location: &'static Location<'static>,
+
+    #[cfg(not(debug_assertions))]
+    phantom_data: PhantomData<&'a Borrowers>,
+}
+
+#[cfg(debug_assertions)]
+impl Drop for BorrowRecord<'_> {
+    fn drop(&mut self) {
+        let mut locations = self.borrowers.locations.lock();
+        if let Some(i) = locations.iter().rposition(|&l| l == self.location) {
+            locations.remove(i);
+        }
+    }
+}
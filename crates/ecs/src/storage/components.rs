@@ -1,51 +1,608 @@
+use std::any::TypeId;
+use std::cmp::Ordering;
+
+use super::index::{ComponentIndex, ErasedIndex, Indexed};
 use super::sparse_set::SparseSet;
+use super::tag_set::{next_bit, TagSet};
 use super::StorageWithEntities;
+use crate::all_storages::AllStorages;
 use crate::prelude::*;
 
-pub trait Component: 'static {}
+/// Data attached to entities.
+///
+/// Components are shared between threads along with the world, so data that
+/// isn't `Send` and `Sync` has to be wrapped in a [`NonSendComponent`], or
+/// stored as a non-send unique.
+pub trait Component: Send + Sync + 'static {
+    /// Update any entity ids held by this component after it has been moved
+    /// to another world.
+    #[inline]
+    fn map_entities(&mut self, _entity_map: &EntityMap) {}
+
+    /// Declare components that an entity must have alongside this one.
+    ///
+    /// These are inserted by [`EntityMut::insert`] if the entity doesn't
+    /// already have them.
+    #[inline]
+    fn required(_required: &mut RequiredComponents) {}
+
+    /// Register lifecycle hooks for this component type, when it is first
+    /// used in a world.
+    #[inline]
+    fn hooks(_hooks: &mut ComponentHooks<Self>)
+    where
+        Self: Sized,
+    {
+    }
+
+    /// How components of this type are stored.
+    const STORAGE: StorageKind = StorageKind::Auto;
+
+    /// Whether the components can be accessed on any thread, so that systems
+    /// using them can too.
+    const IS_SEND: bool = true;
+}
+
+/// How a component type is stored.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum StorageKind {
+    /// Tags for zero-sized components, a sparse set otherwise.
+    #[default]
+    Auto,
+    /// A sparse set with a dense array of components.
+    SparseSet,
+    /// A bitset, which only records which entities have the component.
+    ///
+    /// Only zero-sized components can be stored as tags.
+    Tags,
+}
+
+/// Identifies a component type within a world.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct ComponentId(pub(crate) usize);
+
+impl ComponentId {
+    #[inline]
+    pub fn index(self) -> usize {
+        self.0
+    }
+}
+
+/// A tag for entities that are disabled.
+///
+/// Disabled entities keep their components, but are skipped by joins unless
+/// they opt in with [`Joined::include_disabled`] or [`With<Disabled>`].
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct Disabled;
+
+impl Component for Disabled {}
+
+impl Disabled {
+    /// Every world registers `Disabled` first.
+    pub(crate) const ID: ComponentId = ComponentId(0);
+}
+
+/// Information about a component type registered in a world.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ComponentInfo {
+    pub id: ComponentId,
+    pub type_id: TypeId,
+    pub name: &'static str,
+}
+
+type RequiredComponent = Box<dyn FnOnce(&mut EntityMut)>;
+
+/// The components required by a component type.
+#[derive(Default)]
+pub struct RequiredComponents(Vec<RequiredComponent>);
+
+impl RequiredComponents {
+    /// Require a component, using its default value if it's missing.
+    pub fn add<C: Component + Default>(&mut self) -> &mut Self {
+        self.add_with(C::default)
+    }
+
+    /// Require a component, using `constructor` to create it if it's missing.
+    pub fn add_with<C: Component>(&mut self, constructor: fn() -> C) -> &mut Self {
+        self.0.push(Box::new(move |entity| {
+            if !entity.contains::<C>() {
+                entity.insert_component(constructor());
+            }
+        }));
+        self
+    }
+
+    pub(crate) fn insert_into(self, entity: &mut EntityMut) {
+        for required in self.0 {
+            required(entity);
+        }
+    }
+}
+
+type Hook<C> = Box<dyn Fn(EntityId, &C, &mut Commands) + Send + Sync>;
+
+/// Callbacks that run when components of a given type are added or removed.
+///
+/// Hooks queue any changes they want to make to the world, which are applied
+/// when the world is flushed.
+pub struct ComponentHooks<C: Component> {
+    on_add: Vec<Hook<C>>,
+    on_insert: Vec<Hook<C>>,
+    on_remove: Vec<Hook<C>>,
+}
+
+impl<C: Component> Default for ComponentHooks<C> {
+    fn default() -> Self {
+        Self {
+            on_add: vec![],
+            on_insert: vec![],
+            on_remove: vec![],
+        }
+    }
+}
+
+impl<C: Component> ComponentHooks<C> {
+    /// Add a hook that runs when an entity gains this component.
+    pub fn on_add(
+        &mut self,
+        hook: impl Fn(EntityId, &C, &mut Commands) + Send + Sync + 'static,
+    ) -> &mut Self {
+        self.on_add.push(Box::new(hook));
+        self
+    }
+
+    /// Add a hook that runs whenever this component is inserted, including
+    /// when it replaces an existing component.
+    pub fn on_insert(
+        &mut self,
+        hook: impl Fn(EntityId, &C, &mut Commands) + Send + Sync + 'static,
+    ) -> &mut Self {
+        self.on_insert.push(Box::new(hook));
+        self
+    }
+
+    /// Add a hook that runs when this component is removed, including when
+    /// its entity is despawned.
+    pub fn on_remove(
+        &mut self,
+        hook: impl Fn(EntityId, &C, &mut Commands) + Send + Sync + 'static,
+    ) -> &mut Self {
+        self.on_remove.push(Box::new(hook));
+        self
+    }
+}
+
+/// The entities' components, stored as a bitset for zero-sized components.
+enum Components<C> {
+    Sparse(SparseSet<C>),
+    Tags(TagSet<C>),
+}
+
+macro_rules! dispatch {
+    ($components:expr, $set:ident => $expr:expr) => {
+        match $components {
+            Components::Sparse($set) => $expr,
+            Components::Tags($set) => $expr,
+        }
+    };
+}
+
+impl<C> Components<C> {
+    fn new(kind: StorageKind) -> Self {
+        let is_tag = std::mem::size_of::<C>() == 0;
+        match kind {
+            StorageKind::Auto if is_tag => Self::Tags(TagSet::default()),
+            StorageKind::Auto | StorageKind::SparseSet => Self::Sparse(SparseSet::default()),
+            StorageKind::Tags => {
+                assert!(
+                    is_tag,
+                    "`{}` can't be stored as tags, since it isn't zero-sized",
+                    std::any::type_name::<C>()
+                );
+                Self::Tags(TagSet::default())
+            }
+        }
+    }
+
+    #[inline]
+    fn get(&self, index: usize) -> Option<&C> {
+        dispatch!(self, set => set.get(index))
+    }
+
+    #[inline]
+    fn get_mut(&mut self, index: usize) -> Option<&mut C> {
+        dispatch!(self, set => set.get_mut(index))
+    }
+
+    #[inline]
+    fn insert(&mut self, index: usize, element: C) -> Option<C> {
+        dispatch!(self, set => set.insert(index, element))
+    }
+
+    #[inline]
+    fn remove(&mut self, index: usize) -> Option<C> {
+        dispatch!(self, set => set.remove(index))
+    }
+
+    #[inline]
+    fn iter(&self) -> Iter<'_, C> {
+        dispatch!(self, set => set.iter())
+    }
+
+    #[inline]
+    fn iter_mut(&mut self) -> IterMut<'_, C> {
+        dispatch!(self, set => set.iter_mut())
+    }
+
+    #[inline]
+    fn indexed_iter(&self) -> impl Iterator<Item = (usize, &C)> {
+        let mut cursor = 0;
+        std::iter::from_fn(move || self.next_index(&mut cursor)).zip(self.iter())
+    }
+
+    #[inline]
+    fn indexed_iter_mut(&mut self) -> impl Iterator<Item = (usize, &mut C)> {
+        let (indices, elements) = match self {
+            Self::Sparse(set) => {
+                let (indices, elements) = set.split_mut();
+                (Indices::Dense(indices.iter()), elements)
+            }
+            Self::Tags(set) => {
+                let (bits, tags) = set.split_mut();
+                (Indices::Bits { bits, cursor: 0 }, tags)
+            }
+        };
+        indices.zip(elements)
+    }
+
+    #[inline]
+    fn as_slice(&self) -> &[C] {
+        dispatch!(self, set => set.as_slice())
+    }
+
+    #[inline]
+    fn as_mut_slice(&mut self) -> &mut [C] {
+        dispatch!(self, set => set.as_mut_slice())
+    }
+
+    #[inline]
+    fn contains(&self, index: usize) -> bool {
+        dispatch!(self, set => set.contains(index))
+    }
+
+    #[inline]
+    fn len(&self) -> usize {
+        dispatch!(self, set => set.len())
+    }
+
+    #[inline]
+    fn next_index(&self, cursor: &mut usize) -> Option<usize> {
+        dispatch!(self, set => set.next_index(cursor))
+    }
+
+    #[inline]
+    fn reserve(&mut self, additional: usize) {
+        if let Self::Sparse(set) = self {
+            set.reserve(additional);
+        }
+    }
+
+    #[inline]
+    fn shrink_to_fit(&mut self) {
+        dispatch!(self, set => set.shrink_to_fit())
+    }
+
+    #[inline]
+    fn clear(&mut self) {
+        dispatch!(self, set => set.clear())
+    }
+
+    #[inline]
+    fn allocated_bytes(&self) -> usize {
+        dispatch!(self, set => set.allocated_bytes())
+    }
+
+    // Tags are all equal and always iterate in entity order, so there's
+    // nothing to sort.
+    #[inline]
+    fn sort_by(&mut self, compare: impl FnMut(&C, &C) -> Ordering) {
+        if let Self::Sparse(set) = self {
+            set.sort_by(compare);
+        }
+    }
+
+    #[inline]
+    fn sort_like(&mut self, order: impl IntoIterator<Item = usize>) {
+        if let Self::Sparse(set) = self {
+            set.sort_like(order);
+        }
+    }
+}
+
+/// The entity indices of a [`Components`], in iteration order.
+enum Indices<'a> {
+    Dense(std::slice::Iter<'a, u32>),
+    Bits { bits: &'a [u64], cursor: usize },
+}
+
+impl Iterator for Indices<'_> {
+    type Item = usize;
+
+    #[inline]
+    fn next(&mut self) -> Option<usize> {
+        match self {
+            Indices::Dense(indices) => indices.next().map(|&index| index as usize),
+            Indices::Bits { bits, cursor } => next_bit(bits, cursor),
+        }
+    }
+}
 
-pub struct ComponentStorage<C: Component>(SparseSet<C>);
+pub struct ComponentStorage<C: Component> {
+    /// Set when the storage is registered in a world.
+    id: ComponentId,
+    components: Components<C>,
+    hooks: ComponentHooks<C>,
+    commands: Commands,
+
+    /// Set once the storage is looked up by key, if `C` is [`Indexed`].
+    index: Option<Box<dyn ErasedIndex<C>>>,
+}
 
 impl<C: Component> Default for ComponentStorage<C> {
     fn default() -> Self {
-        ComponentStorage(SparseSet::default())
+        ComponentStorage {
+            id: ComponentId(usize::MAX),
+            components: Components::new(C::STORAGE),
+            hooks: ComponentHooks::default(),
+            commands: Commands::default(),
+            index: None,
+        }
     }
 }
 
 impl<C: Component> ComponentStorage<C> {
+    #[inline]
+    pub fn id(&self) -> ComponentId {
+        self.id
+    }
+
+    #[inline]
+    pub(crate) fn set_id(&mut self, id: ComponentId) {
+        self.id = id;
+    }
+
     #[inline]
     pub fn get(&self, entity: &LiveEntity) -> Option<&C> {
-        self.0.get(entity.index())
+        self.components.get(entity.index())
     }
 
     #[inline]
     pub fn get_mut(&mut self, entity: &LiveEntity) -> Option<&mut C> {
-        self.0.get_mut(entity.index())
+        self.get_mut_by_index(entity.index())
     }
 
-    #[inline]
     pub fn insert(&mut self, entity: &LiveEntity, element: C) -> Option<C> {
-        self.0.insert(entity.index(), element)
+        let prev = self.components.insert(entity.index(), element);
+        entity.set_component(self.id, true);
+
+        let element = self.components.get(entity.index()).unwrap();
+        if let Some(index) = &mut self.index {
+            index.insert(entity.get(), element);
+        }
+        if prev.is_none() {
+            run_hooks(&self.hooks.on_add, entity, element, &mut self.commands);
+        }
+        run_hooks(&self.hooks.on_insert, entity, element, &mut self.commands);
+
+        prev
     }
 
-    #[inline]
     pub fn remove(&mut self, entity: &LiveEntity) -> Option<C> {
-        self.0.remove(entity.index())
+        let element = self.take(entity)?;
+        run_hooks(&self.hooks.on_remove, entity, &element, &mut self.commands);
+        Some(element)
+    }
+
+    /// Remove an entity's component without running any hooks.
+    fn take(&mut self, entity: &LiveEntity) -> Option<C> {
+        let element = self.components.remove(entity.index())?;
+        entity.set_component(self.id, false);
+        if let Some(index) = &mut self.index {
+            index.remove(entity.index());
+        }
+        Some(element)
+    }
+
+    #[inline]
+    pub fn hooks_mut(&mut self) -> &mut ComponentHooks<C> {
+        &mut self.hooks
+    }
+
+    #[inline]
+    pub fn iter(&self) -> Iter<'_, C> {
+        self.components.iter()
+    }
+
+    #[inline]
+    pub fn iter_mut(&mut self) -> IterMut<'_, C> {
+        if let Some(index) = &mut self.index {
+            index.mark_all_dirty();
+        }
+        self.components.iter_mut()
     }
 
+    /// The components as a contiguous slice, in iteration order.
     #[inline]
-    pub fn iter(&self) -> Iter<C> {
-        self.0.iter()
+    pub fn as_slice(&self) -> &[C] {
+        self.components.as_slice()
     }
 
     #[inline]
-    pub fn iter_mut(&mut self) -> IterMut<C> {
-        self.0.iter_mut()
+    pub fn as_mut_slice(&mut self) -> &mut [C] {
+        if let Some(index) = &mut self.index {
+            index.mark_all_dirty();
+        }
+        self.components.as_mut_slice()
     }
 
     #[inline]
     pub fn contains(&self, entity: LiveEntity) -> bool {
-        self.0.contains(entity.index())
+        self.components.contains(entity.index())
+    }
+
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.components.len()
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.components.len() == 0
+    }
+
+    /// Reserve room for at least `additional` more components.
+    #[inline]
+    pub fn reserve(&mut self, additional: usize) {
+        self.components.reserve(additional);
+    }
+
+    /// Release unused capacity, including empty pages of the sparse array.
+    #[inline]
+    pub fn shrink_to_fit(&mut self) {
+        self.components.shrink_to_fit();
+    }
+
+    /// Remove every component, running `on_remove` hooks.
+    pub(crate) fn clear(&mut self, entities: &EntityStorage) {
+        let mut cursor = 0;
+        while let Some(index) = self.components.next_index(&mut cursor) {
+            let entity = entities.entity_to_alive(entities.id_at(index));
+            entity.set_component(self.id, false);
+
+            let element = self.components.get(index).unwrap();
+            run_hooks(&self.hooks.on_remove, &entity, element, &mut self.commands);
+        }
+
+        if let Some(index) = &mut self.index {
+            index.clear();
+        }
+        self.components.clear();
+    }
+
+    pub fn memory_usage(&self) -> MemoryUsage {
+        MemoryUsage {
+            name: std::any::type_name::<C>(),
+            len: self.len(),
+            bytes: self.components.allocated_bytes(),
+        }
+    }
+
+    /// Sort the components, so that iteration (and joins driven by this
+    /// storage) visit them in order. The sort is stable.
+    #[inline]
+    pub fn sort_by(&mut self, compare: impl FnMut(&C, &C) -> Ordering) {
+        self.components.sort_by(compare);
+    }
+
+    #[inline]
+    pub fn sort_by_key<K: Ord>(&mut self, mut key: impl FnMut(&C) -> K) {
+        self.sort_by(|a, b| key(a).cmp(&key(b)));
+    }
+
+    /// Order the components by the entity order of another storage.
+    ///
+    /// Entities that aren't in `other` are moved after the ones that are.
+    /// Sorting storages that are joined together to match keeps the join's
+    /// memory accesses sequential.
+    pub fn sort_like<O: Component>(&mut self, other: &ComponentStorage<O>) {
+        let mut cursor = 0;
+        self.components
+            .sort_like(std::iter::from_fn(|| other.next_index(&mut cursor)));
+    }
+
+    /// Get the index of the next entity in the storage, starting from
+    /// `cursor` (which starts at 0).
+    #[inline]
+    pub(crate) fn next_index(&self, cursor: &mut usize) -> Option<usize> {
+        self.components.next_index(cursor)
+    }
+
+    /// Iterate over the components with the index of their entity.
+    #[inline]
+    pub(crate) fn indexed_iter(&self) -> impl Iterator<Item = (usize, &C)> {
+        self.components.indexed_iter()
+    }
+
+    #[inline]
+    pub(crate) fn indexed_iter_mut(&mut self) -> impl Iterator<Item = (usize, &mut C)> {
+        if let Some(index) = &mut self.index {
+            index.mark_all_dirty();
+        }
+        self.components.indexed_iter_mut()
+    }
+
+    /// Get a component by entity index, for entities known to be alive.
+    #[inline]
+    pub(crate) fn get_by_index(&self, index: usize) -> Option<&C> {
+        self.components.get(index)
+    }
+
+    #[inline]
+    pub(crate) fn get_mut_by_index(&mut self, index: usize) -> Option<&mut C> {
+        let component = self.components.get_mut(index)?;
+        if let Some(key_index) = &mut self.index {
+            key_index.mark_dirty(index);
+        }
+        Some(component)
+    }
+
+    /// Re-index any components that may have been modified since the last
+    /// update.
+    #[inline]
+    pub(crate) fn update_index(&mut self) {
+        if let Some(index) = &mut self.index {
+            let components = &self.components;
+            index.update(&|i| components.get(i));
+        }
+    }
+
+    /// Build the key index from the components already in the storage, if it
+    /// doesn't exist yet.
+    pub(crate) fn build_index(&mut self, entities: &EntityStorage)
+    where
+        C: Indexed,
+    {
+        if self.index.is_some() {
+            return;
+        }
+
+        let mut index = ComponentIndex::<C>::default();
+        let mut cursor = 0;
+        while let Some(i) = self.components.next_index(&mut cursor) {
+            index.insert(entities.id_at(i), self.components.get(i).unwrap());
+        }
+        self.index = Some(Box::new(index));
+    }
+
+    #[inline]
+    pub(crate) fn key_index(&self) -> Option<&ComponentIndex<C>>
+    where
+        C: Indexed,
+    {
+        let index = self.index.as_ref()?;
+        index.as_any().downcast_ref()
+    }
+}
+
+#[inline]
+fn run_hooks<C: Component>(
+    hooks: &[Hook<C>],
+    entity: &LiveEntity,
+    element: &C,
+    commands: &mut Commands,
+) {
+    for hook in hooks {
+        hook(entity.get(), element, commands);
     }
 }
 
@@ -53,4 +610,32 @@ impl<C: Component> StorageWithEntities for ComponentStorage<C> {
     fn remove_entity(&mut self, entity: &LiveEntity) {
         self.remove(entity);
     }
+
+    fn transfer_entity(
+        &mut self,
+        entity: &LiveEntity,
+        dest: &mut AllStorages,
+        dest_entity: EntityId,
+        entity_map: &EntityMap,
+    ) {
+        if let Some(mut component) = self.take(entity) {
+            component.map_entities(entity_map);
+
+            let idx = dest.register_component::<C>();
+            let dest_entity = dest.entities.entity_to_alive(dest_entity);
+            dest.components.get_mut(idx).insert(&dest_entity, component);
+        }
+    }
+
+    fn take_commands(&mut self, commands: &mut Commands) {
+        commands.append(&mut self.commands);
+    }
+
+    fn shrink_to_fit(&mut self) {
+        ComponentStorage::shrink_to_fit(self);
+    }
+
+    fn memory_usage(&self) -> MemoryUsage {
+        ComponentStorage::memory_usage(self)
+    }
 }
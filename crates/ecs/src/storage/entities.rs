@@ -1,40 +1,190 @@
 // THANKS TO: https://skypjack.github.io/2019-05-06-ecs-baf-part-3/
 
-use super::sparse_set::SparseSet;
+use std::collections::HashMap;
+use std::num::NonZeroU64;
+use std::sync::atomic::{AtomicIsize, AtomicU64, Ordering};
 
-#[derive(Clone, Copy, PartialEq, Eq)]
-#[repr(packed)]
-pub struct EntityId {
-    entity: u32,
-    version: u32,
-}
+use super::components::ComponentId;
+use super::MemoryUsage;
+
+/// Identifies an entity: its index, and a version that is incremented each
+/// time the index is reused.
+///
+/// Ids are 8 bytes, as is `Option<EntityId>`.
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+pub struct EntityId(NonZeroU64);
 
-// This is to avoid references to EntityId
-// fields (because it's a packed struct).
 impl EntityId {
+    #[inline]
+    fn new(entity: u32, version: u32) -> Self {
+        // Entity indices are never `u32::MAX`, so this can't overflow.
+        let bits = ((version as u64) << 32) | (entity as u64 + 1);
+        Self(NonZeroU64::new(bits).unwrap())
+    }
+
+    #[inline]
     pub fn entity(self) -> u32 {
-        self.entity
+        self.0.get() as u32 - 1
     }
 
+    #[inline]
     pub fn version(self) -> u32 {
-        self.version
+        (self.0.get() >> 32) as u32
     }
+
+    /// Convert the id to bits, to be sent over the network or stored.
+    ///
+    /// The layout is stable: the version is stored in the high 32 bits, and
+    /// the entity index plus one in the low 32 bits.
+    #[inline]
+    pub fn to_bits(self) -> u64 {
+        self.0.get()
+    }
+
+    /// Convert bits from [`EntityId::to_bits`] back into an id.
+    ///
+    /// Returns `None` if the bits aren't a valid id.
+    #[inline]
+    pub fn from_bits(bits: u64) -> Option<Self> {
+        // The index can't be `u32::MAX`, and the version `u32::MAX` marks
+        // retired entities.
+        if bits as u32 == 0 || (bits >> 32) as u32 == u32::MAX {
+            return None;
+        }
+        NonZeroU64::new(bits).map(Self)
+    }
+}
+
+/// What to do when an entity index has used up all of its versions, and
+/// can't be reused without risking stale ids matching a new entity.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum GenerationOverflow {
+    /// Stop reusing the index. This slowly uses up entity indices.
+    #[default]
+    Retire,
+
+    /// Start again from version 0. Stale ids from long ago may then match
+    /// new entities, so the number of wraps is counted by
+    /// [`World::generation_wraps`](crate::world::World::generation_wraps),
+    /// and each wrap can be reported with
+    /// [`World::on_generation_wrap`](crate::world::World::on_generation_wrap).
+    Wrap,
+
+    /// Refuse to despawn the entity, returning a
+    /// [`GenerationOverflowError`] and leaving the entity untouched.
+    Error,
 }
 
+/// An entity that can't be despawned because it ran out of versions, under
+/// [`GenerationOverflow::Error`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct GenerationOverflowError {
+    pub entity: EntityId,
+}
+
+impl std::fmt::Display for GenerationOverflowError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "entity {:?} ran out of versions", self.entity)
+    }
+}
+
+impl std::error::Error for GenerationOverflowError {}
+
 impl std::fmt::Debug for EntityId {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         write!(f, "{}v{}", self.entity(), self.version())
     }
 }
 
-#[derive(Default)]
+/// A mapping from entities in one world to entities in another.
+///
+/// Produced when entities are moved between worlds, and passed to
+/// [`Component::map_entities`](crate::prelude::Component::map_entities) so
+/// that components can fix up any entity ids they hold.
+#[derive(Debug, Default, Clone)]
+pub struct EntityMap(HashMap<EntityId, EntityId>);
+
+impl EntityMap {
+    /// Get the entity that `entity` was mapped to, if any.
+    #[inline]
+    pub fn get(&self, entity: EntityId) -> Option<EntityId> {
+        self.0.get(&entity).copied()
+    }
+
+    /// Map an entity, leaving it unchanged if it isn't in the map.
+    #[inline]
+    pub fn map(&self, entity: EntityId) -> EntityId {
+        self.get(entity).unwrap_or(entity)
+    }
+
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// Iterate over `(old, new)` pairs.
+    #[inline]
+    pub fn iter(&self) -> impl Iterator<Item = (EntityId, EntityId)> + '_ {
+        self.0.iter().map(|(&from, &to)| (from, to))
+    }
+
+    #[inline]
+    pub(crate) fn insert(&mut self, from: EntityId, to: EntityId) {
+        self.0.insert(from, to);
+    }
+}
+
 pub(crate) struct EntityStorage {
-    next_recycled: u32,
-    num_recycled: usize,
+    /// The current version of each entity.
+    versions: Vec<u32>,
+
+    /// Despawned entities that are waiting to be recycled.
+    recycled: Vec<u32>,
 
-    next: u32,
+    /// The number of recycled entities that haven't been reserved.
+    ///
+    /// Entities are reserved from the end of `recycled`, and once it runs
+    /// out this goes negative to reserve brand new entities.
+    recycled_cursor: AtomicIsize,
+
+    /// Which components each entity has, as a bitset indexed by component id.
+    ///
+    /// Each entity has `signature_len` words, which grows as component types
+    /// are registered. The bits are atomic so that component storages can
+    /// update them while the entities are borrowed immutably.
+    signatures: Vec<AtomicU64>,
+    signature_len: usize,
 
-    storage: SparseSet<EntityId>,
+    /// The highest version an entity can have before it overflows.
+    max_version: u32,
+    on_overflow: GenerationOverflow,
+
+    /// How many times an entity's version has wrapped back to 0.
+    generation_wraps: usize,
+    on_wrap: Option<WrapHook>,
+}
+
+type WrapHook = Box<dyn FnMut(EntityId) + Send + Sync>;
+
+impl Default for EntityStorage {
+    fn default() -> Self {
+        Self {
+            versions: vec![],
+            recycled: vec![],
+            recycled_cursor: AtomicIsize::new(0),
+            signatures: vec![],
+            signature_len: 0,
+            max_version: u32::MAX - 1,
+            on_overflow: GenerationOverflow::default(),
+            generation_wraps: 0,
+            on_wrap: None,
+        }
+    }
 }
 
 impl EntityStorage {
@@ -42,100 +192,372 @@ impl EntityStorage {
     ///
     /// Panics if out of entities.
     pub fn spawn(&mut self) -> EntityId {
-        if self.num_recycled > 0 {
+        self.flush();
+
+        let entity = match self.recycled.pop() {
             // There is an entity waiting for us to use.
+            Some(entity) => {
+                *self.recycled_cursor.get_mut() = self.recycled.len() as isize;
+                entity
+            }
 
-            let stored = self.storage.get_mut(self.next_recycled as usize).unwrap();
+            // There are no entities we can reuse, so we need to
+            // assign a new one.
+            None => {
+                let entity = new_entity_index(self.versions.len());
+                self.versions.push(0);
+                self.grow_signatures();
+                entity
+            }
+        };
+
+        EntityId::new(entity, self.versions[entity as usize])
+    }
 
-            // Remove it from the implicit linked list.
-            std::mem::swap(&mut self.next_recycled, &mut stored.entity());
-            self.num_recycled -= 1;
+    /// Reserve an entity without needing exclusive access.
+    ///
+    /// The entity can be referenced straight away, but only exists once the
+    /// storage is flushed.
+    ///
+    /// Panics if out of entities.
+    pub fn reserve(&self) -> EntityId {
+        let cursor = self.recycled_cursor.fetch_sub(1, Ordering::Relaxed);
 
-            *stored
+        if cursor > 0 {
+            let entity = self.recycled[cursor as usize - 1];
+            EntityId::new(entity, self.versions[entity as usize])
         } else {
-            // There are no entities we can reuse, so we need to
-            // assign a new one.
+            // Brand new entities are handed out past the end of the
+            // storage, in the order they were reserved.
+            let entity = new_entity_index(self.versions.len() + cursor.unsigned_abs());
+            EntityId::new(entity, 0)
+        }
+    }
 
-            if self.next == u32::MAX {
-                panic!("out of entities.");
-            }
+    /// Materialise any reserved entities.
+    pub fn flush(&mut self) {
+        let cursor = self.recycled_cursor.get_mut();
 
-            let entity = self.next;
-            self.next += 1;
+        if *cursor < 0 {
+            let num_new = cursor.unsigned_abs();
+            self.recycled.clear();
+            self.versions.extend(std::iter::repeat_n(0, num_new));
+        } else {
+            self.recycled.truncate(*cursor as usize);
+        }
 
-            let entity = EntityId { entity, version: 0 };
+        *cursor = self.recycled.len() as isize;
 
-            self.storage.insert(entity.entity as usize, entity);
+        self.grow_signatures();
+    }
 
-            entity
+    /// Check that an entity can be despawned, which it can't if it has run
+    /// out of versions under [`GenerationOverflow::Error`].
+    ///
+    /// Panics if the entity isn't in this storage.
+    pub fn check_despawn(&self, entity: EntityId) -> Result<(), GenerationOverflowError> {
+        let version = self.versions[entity.entity() as usize];
+        if version == self.max_version && self.on_overflow == GenerationOverflow::Error {
+            Err(GenerationOverflowError { entity })
+        } else {
+            Ok(())
         }
     }
 
     /// Despawn an entity.
     ///
+    /// Returns an error without despawning the entity if it can't be
+    /// despawned, see [`EntityStorage::check_despawn`].
+    ///
     /// Panics if the entity isn't in this storage, or this entity was already
     /// despawned.
-    pub fn despawn(&mut self, entity: EntityId) {
-        let stored = self.storage.get_mut(entity.entity as usize).unwrap();
+    pub fn despawn(&mut self, entity: EntityId) -> Result<(), GenerationOverflowError> {
+        self.flush();
 
         // Make sure this isn't a dead entity.
-        if stored.version != entity.version {
+        if self.versions[entity.entity() as usize] != entity.version() {
             panic!("tried to despawn entity {entity:?} twice");
         }
 
-        // Increment the version.
-        // Version will not be greater than `u32::MAX` - 1, so it won't wrap.
-        stored.version += 1;
+        self.check_despawn(entity)?;
+        let version = &mut self.versions[entity.entity() as usize];
 
-        // Recycle this id if possible by adding it to the implicit linked list.
-        // It can't be reused if its new version is `u32::MAX`, because its
-        // version wouldn't be incrementable when it was despawned.
-        if stored.version < u32::MAX {
-            std::mem::swap(&mut self.next_recycled, &mut stored.entity());
-            self.num_recycled += 1;
+        let signature = entity.entity() as usize * self.signature_len;
+        for word in &mut self.signatures[signature..signature + self.signature_len] {
+            *word.get_mut() = 0;
         }
+
+        if *version < self.max_version {
+            *version += 1;
+        } else {
+            match self.on_overflow {
+                // Retired entities are marked with a version of `u32::MAX`,
+                // which is never handed out.
+                GenerationOverflow::Retire => {
+                    *version = u32::MAX;
+                    return Ok(());
+                }
+                GenerationOverflow::Wrap => {
+                    *version = 0;
+                    self.generation_wraps += 1;
+                    if let Some(on_wrap) = &mut self.on_wrap {
+                        on_wrap(entity);
+                    }
+                }
+                GenerationOverflow::Error => unreachable!("checked above"),
+            }
+        }
+
+        self.recycled.push(entity.entity());
+        *self.recycled_cursor.get_mut() = self.recycled.len() as isize;
+        Ok(())
+    }
+
+    /// How many times an entity's version has wrapped back to 0, under
+    /// [`GenerationOverflow::Wrap`].
+    #[inline]
+    pub fn generation_wraps(&self) -> usize {
+        self.generation_wraps
+    }
+
+    /// Call `hook` with each despawned entity whose version wraps back to 0.
+    #[inline]
+    pub fn on_generation_wrap(&mut self, hook: impl FnMut(EntityId) + Send + Sync + 'static) {
+        self.on_wrap = Some(Box::new(hook));
+    }
+
+    /// Limit entity versions to `bits` bits, and set what happens when an
+    /// entity runs out of versions.
+    ///
+    /// Panics if `bits` isn't between 1 and 32, or any entities have been
+    /// spawned.
+    pub fn set_generations(&mut self, bits: u32, on_overflow: GenerationOverflow) {
+        assert!(
+            (1..=32).contains(&bits),
+            "entity versions must have between 1 and 32 bits"
+        );
+        assert!(
+            self.versions.is_empty() && *self.recycled_cursor.get_mut() == 0,
+            "entity generations can't be changed after spawning entities"
+        );
+
+        // `u32::MAX` marks retired entities.
+        self.max_version = if bits == 32 {
+            u32::MAX - 1
+        } else {
+            (1 << bits) - 1
+        };
+        self.on_overflow = on_overflow;
+    }
+
+    /// Collect all live entities.
+    ///
+    /// Reserved entities are only included once the storage is flushed.
+    pub fn alive(&self) -> Vec<EntityId> {
+        let mut dead = vec![false; self.versions.len()];
+        for &entity in &self.recycled {
+            dead[entity as usize] = true;
+        }
+
+        self.versions
+            .iter()
+            .enumerate()
+            .filter(|&(entity, _)| !dead[entity])
+            // Retired entities have a version of `u32::MAX`, and are never
+            // recycled.
+            .filter(|&(_, &version)| version != u32::MAX)
+            .map(|(entity, &version)| EntityId::new(entity as u32, version))
+            .collect()
+    }
+
+    /// Reserve room for at least `additional` more entities.
+    pub fn reserve_capacity(&mut self, additional: usize) {
+        self.versions.reserve(additional);
+        self.signatures.reserve(additional * self.signature_len);
+    }
+
+    /// Release unused capacity.
+    ///
+    /// Despawned entities keep their slot, so that their ids can't be
+    /// confused with a recycled entity.
+    pub fn shrink_to_fit(&mut self) {
+        self.versions.shrink_to_fit();
+        self.recycled.shrink_to_fit();
+        self.signatures.shrink_to_fit();
+    }
+
+    pub fn memory_usage(&self) -> MemoryUsage {
+        MemoryUsage {
+            name: "entities",
+            len: self.versions.len() - self.recycled.len(),
+            bytes: (self.versions.capacity() + self.recycled.capacity())
+                * std::mem::size_of::<u32>()
+                + self.signatures.capacity() * std::mem::size_of::<AtomicU64>(),
+        }
+    }
+
+    /// Make room in every entity's signature for a component type.
+    pub fn register_component(&mut self, id: ComponentId) {
+        let len = id.index() / 64 + 1;
+        if len <= self.signature_len {
+            return;
+        }
+
+        let mut signatures = Vec::with_capacity(self.versions.len() * len);
+        for signature in self.signatures.chunks_mut(self.signature_len.max(1)) {
+            signatures.extend(
+                signature
+                    .iter_mut()
+                    .map(|word| AtomicU64::new(*word.get_mut())),
+            );
+            signatures.extend((signature.len()..len).map(|_| AtomicU64::new(0)));
+        }
+
+        self.signatures = signatures;
+        self.signature_len = len;
+        self.grow_signatures();
+    }
+
+    /// Add empty signatures for any new entities.
+    fn grow_signatures(&mut self) {
+        let len = self.versions.len() * self.signature_len;
+        self.signatures.resize_with(len, || AtomicU64::new(0));
+    }
+
+    /// Check if an entity has a component.
+    #[inline]
+    pub fn has_component(&self, entity: &LiveEntity, id: ComponentId) -> bool {
+        let (word, bit) = (id.index() / 64, id.index() % 64);
+        if word >= self.signature_len {
+            return false;
+        }
+
+        let word = &self.signatures[entity.index() * self.signature_len + word];
+        word.load(Ordering::Relaxed) & (1 << bit) != 0
+    }
+
+    /// Record whether an entity has a component.
+    ///
+    /// Panics if the component type isn't registered.
+    #[inline]
+    pub(crate) fn set_component(&self, entity: &LiveEntity, id: ComponentId, present: bool) {
+        let (word, bit) = (id.index() / 64, id.index() % 64);
+        assert!(word < self.signature_len, "component {id:?} not registered");
+
+        let word = &self.signatures[entity.index() * self.signature_len + word];
+        if present {
+            word.fetch_or(1 << bit, Ordering::Relaxed);
+        } else {
+            word.fetch_and(!(1 << bit), Ordering::Relaxed);
+        }
+    }
+
+    /// Get a word of the signature of the entity at `index`.
+    #[inline]
+    pub fn signature_word(&self, index: usize, word: usize) -> u64 {
+        if word >= self.signature_len {
+            return 0;
+        }
+        self.signatures[index * self.signature_len + word].load(Ordering::Relaxed)
+    }
+
+    /// Get the id of the live entity at `index`.
+    #[inline]
+    pub fn id_at(&self, index: usize) -> EntityId {
+        EntityId::new(index as u32, self.versions[index])
+    }
+
+    /// Iterate over the components an entity has.
+    pub fn components(&self, entity: &LiveEntity) -> impl Iterator<Item = ComponentId> + '_ {
+        let start = entity.index() * self.signature_len;
+        let signature = &self.signatures[start..start + self.signature_len];
+
+        signature.iter().enumerate().flat_map(|(word_index, word)| {
+            let word = word.load(Ordering::Relaxed);
+            (0..64)
+                .filter(move |bit| word & (1 << bit) != 0)
+                .map(move |bit| ComponentId(word_index * 64 + bit))
+        })
     }
 
     /// Check if an entity is alive.
     ///
+    /// Entities that have been reserved but not flushed yet aren't alive.
     /// Panics if the entity isn't in this storage.
     #[inline]
     pub fn is_alive(&self, entity: EntityId) -> bool {
-        self.storage.get(entity.entity as usize).unwrap().version == entity.version
+        match self.versions.get(entity.entity() as usize) {
+            Some(&version) => version == entity.version() && !self.is_reserved(entity),
+            None if self.is_reserved(entity) => false,
+            None => panic!("entity {entity:?} not in this storage"),
+        }
     }
 
-    /// Try to convert this to a live entity.
-    #[inline]
-    pub fn try_entity_to_alive(&self, entity: EntityId) -> Option<LiveEntity> {
-        if !self.storage.contains(entity.entity as usize) {
-            panic!("entity {entity:?} not in this storage");
+    /// Check if an entity was handed out by [`EntityStorage::reserve`], and
+    /// the storage hasn't been flushed since.
+    fn is_reserved(&self, entity: EntityId) -> bool {
+        let cursor = self.recycled_cursor.load(Ordering::Relaxed);
+        let index = entity.entity() as usize;
+
+        // Brand new entities are reserved past the end of the storage.
+        if index >= self.versions.len() {
+            return index < self.versions.len() + cursor.min(0).unsigned_abs();
         }
 
+        // Recycled entities are reserved from the end of `recycled`, and
+        // already have the version they'll be spawned with.
+        self.versions[index] == entity.version()
+            && self.recycled[cursor.max(0) as usize..].contains(&entity.entity())
+    }
+
+    /// Try to convert this to a live entity.
+    #[inline]
+    pub fn try_entity_to_alive(&self, entity: EntityId) -> Option<LiveEntity<'_>> {
         if self.is_alive(entity) {
             Some(LiveEntity {
                 entity,
-                _storage: self,
+                storage: self,
             })
         } else {
             None
         }
     }
 
+    /// Convert this to a live entity, or `None` if it has been reserved but
+    /// the storage hasn't been flushed yet.
+    ///
+    /// Panics if the entity is dead.
+    #[inline]
+    pub fn entity_to_spawned(&self, entity: EntityId) -> Option<LiveEntity<'_>> {
+        if self.is_reserved(entity) {
+            None
+        } else {
+            Some(self.entity_to_alive(entity))
+        }
+    }
+
     /// Convert this to a live entity.
     ///
     /// Panics if the entity is not alive.
     #[inline]
-    pub fn entity_to_alive(&self, entity: EntityId) -> LiveEntity {
+    pub fn entity_to_alive(&self, entity: EntityId) -> LiveEntity<'_> {
         self.try_entity_to_alive(entity)
             .unwrap_or_else(|| panic!("entity {entity:?} not alive"))
     }
 }
 
+fn new_entity_index(index: usize) -> u32 {
+    match u32::try_from(index) {
+        Ok(index) if index < u32::MAX => index,
+        _ => panic!("out of entities."),
+    }
+}
+
 /// An alive entity.
 #[doc(hidden)]
 pub struct LiveEntity<'a> {
     entity: EntityId,
-    _storage: &'a EntityStorage,
+    storage: &'a EntityStorage,
 }
 
 impl<'a> LiveEntity<'a> {
@@ -146,4 +568,14 @@ impl<'a> LiveEntity<'a> {
     pub(super) fn index(&self) -> usize {
         self.entity.entity() as usize
     }
+
+    #[inline]
+    pub fn has_component(&self, id: ComponentId) -> bool {
+        self.storage.has_component(self, id)
+    }
+
+    #[inline]
+    pub(crate) fn set_component(&self, id: ComponentId, present: bool) {
+        self.storage.set_component(self, id, present);
+    }
 }
@@ -0,0 +1,177 @@
+use std::any::Any;
+use std::collections::HashMap;
+use std::fmt;
+use std::hash::Hash;
+
+use crate::prelude::*;
+
+/// A component that entities can be looked up by, with [`KeyIndex`].
+///
+/// The index is built the first time it's borrowed, then kept up to date as
+/// components are inserted, modified and removed.
+pub trait Indexed: Component {
+    type Key: Hash + Eq + Clone + Send + Sync + 'static;
+
+    /// Whether each key should belong to at most one entity. Looking up a
+    /// key shared by several entities is then an error, rather than
+    /// returning whichever was indexed first.
+    const UNIQUE: bool = false;
+
+    fn key(&self) -> Self::Key;
+}
+
+/// Several entities share a key of a component with [`Indexed::UNIQUE`] set.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DuplicateKeyError {
+    pub type_name: &'static str,
+    pub entities: Vec<EntityId>,
+}
+
+impl fmt::Display for DuplicateKeyError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "entities {:?} share a key of unique component {}",
+            self.entities, self.type_name
+        )
+    }
+}
+
+impl std::error::Error for DuplicateKeyError {}
+
+/// The type-erased index stored alongside a component storage.
+pub(crate) trait ErasedIndex<C>: Any + Send + Sync {
+    /// Index a component that was just inserted, replacing its old key.
+    fn insert(&mut self, entity: EntityId, component: &C);
+
+    fn remove(&mut self, index: usize);
+
+    fn clear(&mut self);
+
+    /// Remember that a component may have been modified, so it can be
+    /// re-indexed with [`ErasedIndex::update`].
+    fn mark_dirty(&mut self, index: usize);
+
+    fn mark_all_dirty(&mut self);
+
+    /// Re-index every component that was marked dirty.
+    fn update<'c>(&mut self, get: &dyn Fn(usize) -> Option<&'c C>);
+
+    fn as_any(&self) -> &dyn Any;
+}
+
+pub(crate) struct ComponentIndex<C: Indexed> {
+    entities: HashMap<C::Key, Vec<EntityId>>,
+
+    /// The key each entity was indexed under, by entity index.
+    keys: HashMap<usize, (EntityId, C::Key)>,
+
+    /// The entity indices marked dirty, and a bitset of them so that each
+    /// is only recorded once.
+    dirty: Vec<usize>,
+    dirty_bits: Vec<u64>,
+    all_dirty: bool,
+}
+
+impl<C: Indexed> Default for ComponentIndex<C> {
+    fn default() -> Self {
+        ComponentIndex {
+            entities: HashMap::new(),
+            keys: HashMap::new(),
+            dirty: Vec::new(),
+            dirty_bits: Vec::new(),
+            all_dirty: false,
+        }
+    }
+}
+
+impl<C: Indexed> ComponentIndex<C> {
+    #[inline]
+    pub(crate) fn get_all(&self, key: &C::Key) -> &[EntityId] {
+        self.entities.get(key).map_or(&[], Vec::as_slice)
+    }
+
+    fn add(&mut self, entity: EntityId, key: C::Key) {
+        self.entities.entry(key.clone()).or_default().push(entity);
+        self.keys.insert(entity.entity() as usize, (entity, key));
+    }
+}
+
+impl<C: Indexed> ErasedIndex<C> for ComponentIndex<C> {
+    fn insert(&mut self, entity: EntityId, component: &C) {
+        self.remove(entity.entity() as usize);
+        self.add(entity, component.key());
+    }
+
+    fn remove(&mut self, index: usize) {
+        let Some((entity, key)) = self.keys.remove(&index) else {
+            return;
+        };
+
+        let entities = self.entities.get_mut(&key).unwrap();
+        entities.retain(|&other| other != entity);
+        if entities.is_empty() {
+            self.entities.remove(&key);
+        }
+    }
+
+    fn clear(&mut self) {
+        self.entities.clear();
+        self.keys.clear();
+        self.dirty.clear();
+        self.dirty_bits.clear();
+        self.all_dirty = false;
+    }
+
+    #[inline]
+    fn mark_dirty(&mut self, index: usize) {
+        if self.all_dirty {
+            return;
+        }
+
+        let (word, bit) = (index / 64, index % 64);
+        if word >= self.dirty_bits.len() {
+            self.dirty_bits.resize(word + 1, 0);
+        }
+        if self.dirty_bits[word] & (1 << bit) == 0 {
+            self.dirty_bits[word] |= 1 << bit;
+            self.dirty.push(index);
+        }
+    }
+
+    #[inline]
+    fn mark_all_dirty(&mut self) {
+        self.all_dirty = true;
+        self.dirty.clear();
+        self.dirty_bits.clear();
+    }
+
+    fn update<'c>(&mut self, get: &dyn Fn(usize) -> Option<&'c C>) {
+        let mut dirty = std::mem::take(&mut self.dirty);
+        self.dirty_bits.fill(0);
+        if std::mem::take(&mut self.all_dirty) {
+            dirty.extend(self.keys.keys().copied());
+        }
+
+        for index in dirty.drain(..) {
+            let Some(component) = get(index) else {
+                continue;
+            };
+            let key = component.key();
+            if self.keys.get(&index).is_some_and(|(_, old)| *old == key) {
+                continue;
+            }
+
+            let entity = self.keys[&index].0;
+            self.remove(index);
+            self.add(entity, key);
+        }
+
+        self.dirty = dirty;
+    }
+
+    #[inline]
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+}
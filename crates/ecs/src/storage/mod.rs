@@ -1,21 +1,154 @@
+mod borrow;
 pub mod components;
 pub mod entities;
+pub mod index;
+pub mod non_send;
 pub mod unique;
 
 mod sparse_set;
+mod tag_set;
 
-use self::entities::LiveEntity;
+use std::fmt;
+use std::panic::Location;
+
+pub(crate) use self::borrow::Borrowers;
+pub use self::borrow::{Ref, RefMut};
+use self::entities::{EntityId, EntityMap, LiveEntity};
 pub use self::sparse_set::{Iter, IterMut};
+use crate::access::AccessConflict;
+use crate::all_storages::AllStorages;
+use crate::commands::Commands;
 
-pub trait StorageWithEntities: 'static {
+pub trait StorageWithEntities: Send + Sync + 'static {
     fn remove_entity(&mut self, entity: &LiveEntity);
+
+    /// Move an entity's data into the storages of another world.
+    fn transfer_entity(
+        &mut self,
+        entity: &LiveEntity,
+        dest: &mut AllStorages,
+        dest_entity: EntityId,
+        entity_map: &EntityMap,
+    );
+
+    /// Move any commands queued by the storage into `commands`.
+    fn take_commands(&mut self, commands: &mut Commands);
+
+    fn shrink_to_fit(&mut self);
+
+    fn memory_usage(&self) -> MemoryUsage;
+}
+
+/// How much memory a storage is using, from [`World::memory_usage`].
+///
+/// [`World::memory_usage`]: crate::prelude::World::memory_usage
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MemoryUsage {
+    /// The component type, or `"entities"` for the entities themselves.
+    pub name: &'static str,
+
+    /// The number of entities in the storage.
+    pub len: usize,
+
+    /// The number of bytes allocated, excluding memory owned by the
+    /// components themselves and key indices.
+    pub bytes: usize,
+}
+
+/// Whether something is borrowed immutably or mutably.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BorrowKind {
+    Shared,
+    Exclusive,
+}
+
+impl fmt::Display for BorrowKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Shared => write!(f, "immutably"),
+            Self::Exclusive => write!(f, "mutably"),
+        }
+    }
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub enum BorrowError {
-    ResourceNotFound,
-    StorageNotFound,
-    InvalidBorrow,
+    /// The unique doesn't exist, or was removed.
+    ResourceNotFound { type_name: &'static str },
+
+    /// The storage hasn't been created yet.
+    StorageNotFound { type_name: &'static str },
+
+    /// The storage is already borrowed in a way that conflicts with this
+    /// borrow.
+    InvalidBorrow {
+        type_name: &'static str,
+
+        /// How the storage was being borrowed.
+        requested: BorrowKind,
+
+        /// How the storage is already borrowed.
+        held: BorrowKind,
+
+        /// Where the conflicting borrow was made, or the most recent one if
+        /// there are several (only tracked in debug builds).
+        borrower: Option<&'static Location<'static>>,
+    },
+
+    /// A system borrows the same data mutably and immutably.
+    AccessConflict(AccessConflict),
+
+    /// The data isn't `Send` and belongs to another thread.
+    WrongThread { type_name: &'static str },
+}
+
+impl BorrowError {
+    /// The name of the type that couldn't be borrowed.
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            Self::ResourceNotFound { type_name }
+            | Self::StorageNotFound { type_name }
+            | Self::InvalidBorrow { type_name, .. }
+            | Self::WrongThread { type_name } => type_name,
+            Self::AccessConflict(conflict) => conflict.type_name,
+        }
+    }
+}
+
+impl fmt::Display for BorrowError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::ResourceNotFound { type_name } => write!(f, "unique {type_name} not found"),
+            Self::StorageNotFound { type_name } => write!(f, "storage {type_name} not found"),
+            Self::InvalidBorrow {
+                type_name,
+                requested,
+                held,
+                borrower,
+            } => {
+                write!(
+                    f,
+                    "can't borrow {type_name} {requested}, it is already borrowed {held}"
+                )?;
+                if let Some(borrower) = borrower {
+                    write!(f, " (borrowed at {borrower})")?;
+                }
+                Ok(())
+            }
+            Self::AccessConflict(conflict) => conflict.fmt(f),
+            Self::WrongThread { type_name } => {
+                write!(f, "{type_name} can only be accessed on its world's thread")
+            }
+        }
+    }
+}
+
+impl std::error::Error for BorrowError {}
+
+impl From<AccessConflict> for BorrowError {
+    fn from(conflict: AccessConflict) -> Self {
+        Self::AccessConflict(conflict)
+    }
 }
 
 pub type BorrowResult<T> = Result<T, BorrowError>;
@@ -0,0 +1,83 @@
+use std::fmt;
+use std::ops::{Deref, DerefMut};
+
+use send_wrapper::SendWrapper;
+
+use super::components::Component;
+
+/// A component holding data that isn't `Send` or `Sync`.
+///
+/// The data can only be accessed on the thread that created the component:
+/// [`NonSendComponent::try_get`] returns `None` on other threads, and
+/// dereferencing or dropping the component there panics. Queries for these
+/// components make systems non-send, so schedules keep them on their own
+/// thread.
+pub struct NonSendComponent<T: 'static>(SendWrapper<T>);
+
+impl<T: 'static> NonSendComponent<T> {
+    #[inline]
+    pub fn new(value: T) -> Self {
+        Self(SendWrapper::new(value))
+    }
+
+    /// Whether the data can be accessed on this thread.
+    #[inline]
+    pub fn is_accessible(&self) -> bool {
+        self.0.valid()
+    }
+
+    #[inline]
+    pub fn try_get(&self) -> Option<&T> {
+        self.is_accessible().then(|| &*self.0)
+    }
+
+    #[inline]
+    pub fn try_get_mut(&mut self) -> Option<&mut T> {
+        if self.is_accessible() {
+            Some(&mut *self.0)
+        } else {
+            None
+        }
+    }
+
+    /// Take the data out of the component.
+    ///
+    /// Panics on another thread than the one that created the component.
+    #[inline]
+    pub fn into_inner(self) -> T {
+        self.0.take()
+    }
+}
+
+impl<T: 'static> Component for NonSendComponent<T> {
+    const IS_SEND: bool = false;
+}
+
+impl<T: 'static> Deref for NonSendComponent<T> {
+    type Target = T;
+
+    #[inline]
+    #[track_caller]
+    fn deref(&self) -> &Self::Target {
+        self.try_get()
+            .expect("non-send component accessed on another thread")
+    }
+}
+
+impl<T: 'static> DerefMut for NonSendComponent<T> {
+    #[inline]
+    #[track_caller]
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        self.try_get_mut()
+            .expect("non-send component accessed on another thread")
+    }
+}
+
+impl<T: fmt::Debug + 'static> fmt::Debug for NonSendComponent<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.try_get() {
+            Some(value) => f.debug_tuple("NonSendComponent").field(value).finish(),
+            None => f.write_str("NonSendComponent(<other thread>)"),
+        }
+    }
+}
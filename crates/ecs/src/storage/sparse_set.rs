@@ -1,22 +1,45 @@
+use std::cmp::Ordering;
+use std::num::NonZeroU32;
+
 const PAGE_SIZE: usize = 64;
 
-#[derive(Debug)]
-struct DenseEntry<T> {
-    sparse_index: usize,
-    element: T,
+/// A `u32` that can't be `u32::MAX`, so that `Option<NonMaxU32>` is the same
+/// size as a `u32`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct NonMaxU32(NonZeroU32);
+
+impl NonMaxU32 {
+    /// Panics if `value` is `u32::MAX`.
+    #[inline]
+    fn new(value: usize) -> Self {
+        let value = u32::try_from(value).ok().filter(|&value| value != u32::MAX);
+        let value = value.expect("sparse set index out of range");
+        Self(NonZeroU32::new(value ^ u32::MAX).unwrap())
+    }
+
+    #[inline]
+    fn get(self) -> usize {
+        (self.0.get() ^ u32::MAX) as usize
+    }
 }
 
+/// The dense array is stored as a structure of arrays, so that the elements
+/// are contiguous.
 #[derive(Debug)]
 pub(crate) struct SparseSet<T> {
     sparse: SparseArray,
-    dense: Vec<DenseEntry<T>>,
+
+    /// The sparse index of each element.
+    indices: Vec<u32>,
+    elements: Vec<T>,
 }
 
 impl<T> Default for SparseSet<T> {
     fn default() -> Self {
         Self {
             sparse: SparseArray::default(),
-            dense: vec![],
+            indices: vec![],
+            elements: vec![],
         }
     }
 }
@@ -25,103 +48,237 @@ impl<T> SparseSet<T> {
     #[inline]
     pub fn get(&self, index: usize) -> Option<&T> {
         let dense_index = self.sparse.get(index)?;
-        Some(&self.dense[dense_index].element)
+        Some(&self.elements[dense_index])
     }
 
     #[inline]
     pub fn get_mut(&mut self, index: usize) -> Option<&mut T> {
         let dense_index = self.sparse.get(index)?;
-        Some(&mut self.dense[dense_index].element)
+        Some(&mut self.elements[dense_index])
     }
 
     pub fn insert(&mut self, index: usize, element: T) -> Option<T> {
-        let entry = DenseEntry {
-            sparse_index: index,
-            element,
-        };
-
         match self.sparse.get(index) {
-            Some(dense_index) => {
-                let prev = std::mem::replace(&mut self.dense[dense_index], entry);
-                Some(prev.element)
-            }
+            Some(dense_index) => Some(std::mem::replace(&mut self.elements[dense_index], element)),
             None => {
-                self.dense.push(entry);
-                self.sparse.set(index, self.dense.len() - 1);
+                self.sparse.set(index, self.elements.len());
+                self.indices.push(index as u32);
+                self.elements.push(element);
                 None
             }
         }
     }
 
     pub fn remove(&mut self, index: usize) -> Option<T> {
-        match self.sparse.get(index) {
-            Some(dense_index) => {
-                // Swap-remove the element from the dense-array.
-                let removed = self.dense.swap_remove(dense_index);
-
-                // If we swapped an element, update sparse-array entry for the swapped
-                // element now located where the removed element previously was.
-                if dense_index != self.dense.len() {
-                    let sparse_swapped_index = self.dense[dense_index].sparse_index;
-                    self.sparse.set(sparse_swapped_index, index);
-                }
+        let dense_index = self.sparse.get(index)?;
 
-                // Remove the sparse-array entry for the removed element.
-                self.sparse.remove(index);
+        // Swap-remove the element from the dense arrays.
+        self.indices.swap_remove(dense_index);
+        let removed = self.elements.swap_remove(dense_index);
 
-                Some(removed.element)
-            }
-            None => None,
+        // If we swapped an element, update sparse-array entry for the swapped
+        // element now located where the removed element previously was.
+        if let Some(&sparse_swapped_index) = self.indices.get(dense_index) {
+            self.sparse.set(sparse_swapped_index as usize, dense_index);
         }
+
+        // Remove the sparse-array entry for the removed element.
+        self.sparse.remove(index);
+
+        Some(removed)
     }
 
     #[inline]
-    pub fn iter(&self) -> Iter<T> {
-        Iter(self.dense.iter())
+    pub fn iter(&self) -> Iter<'_, T> {
+        Iter(self.elements.iter())
     }
 
     #[inline]
-    pub fn iter_mut(&mut self) -> IterMut<T> {
-        IterMut(self.dense.iter_mut())
+    pub fn iter_mut(&mut self) -> IterMut<'_, T> {
+        IterMut(self.elements.iter_mut())
+    }
+
+    /// The sparse index of each element, and the elements.
+    #[inline]
+    pub fn split_mut(&mut self) -> (&[u32], &mut [T]) {
+        (&self.indices, &mut self.elements)
+    }
+
+    /// The elements, in the order of the dense array.
+    #[inline]
+    pub fn as_slice(&self) -> &[T] {
+        &self.elements
+    }
+
+    #[inline]
+    pub fn as_mut_slice(&mut self) -> &mut [T] {
+        &mut self.elements
     }
 
     #[inline]
     pub fn contains(&self, index: usize) -> bool {
         self.sparse.get(index).is_some()
     }
+
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.elements.len()
+    }
+
+    #[inline]
+    pub fn reserve(&mut self, additional: usize) {
+        self.indices.reserve(additional);
+        self.elements.reserve(additional);
+    }
+
+    pub fn shrink_to_fit(&mut self) {
+        self.indices.shrink_to_fit();
+        self.elements.shrink_to_fit();
+        self.sparse.shrink_to_fit();
+    }
+
+    pub fn clear(&mut self) {
+        self.indices.clear();
+        self.elements.clear();
+        self.sparse.pages.clear();
+    }
+
+    /// The number of bytes allocated by the set.
+    pub fn allocated_bytes(&self) -> usize {
+        self.indices.capacity() * std::mem::size_of::<u32>()
+            + self.elements.capacity() * std::mem::size_of::<T>()
+            + self.sparse.allocated_bytes()
+    }
+
+    /// Sort the dense array, keeping equal elements in their current order.
+    pub fn sort_by(&mut self, mut compare: impl FnMut(&T, &T) -> Ordering) {
+        let mut order: Vec<usize> = (0..self.len()).collect();
+        order.sort_by(|&a, &b| compare(&self.elements[a], &self.elements[b]));
+
+        // Move each element to its sorted position, one cycle of the
+        // permutation at a time.
+        let mut placed = vec![false; order.len()];
+        for start in 0..order.len() {
+            if placed[start] {
+                continue;
+            }
+
+            let mut current = start;
+            while order[current] != start {
+                let next = order[current];
+                self.swap(current, next);
+                placed[current] = true;
+                current = next;
+            }
+            placed[current] = true;
+        }
+
+        for (dense_index, &index) in self.indices.iter().enumerate() {
+            self.sparse.set(index as usize, dense_index);
+        }
+    }
+
+    /// Move the elements at the given sparse indices to the front of the
+    /// dense array, in that order. Indices that aren't in the set are
+    /// skipped, and the remaining elements end up after the sorted ones.
+    pub fn sort_like(&mut self, order: impl IntoIterator<Item = usize>) {
+        let mut next = 0;
+        for index in order {
+            let Some(dense_index) = self.sparse.get(index) else {
+                continue;
+            };
+
+            if dense_index != next {
+                self.swap(dense_index, next);
+                self.sparse
+                    .set(self.indices[dense_index] as usize, dense_index);
+                self.sparse.set(index, next);
+            }
+            next += 1;
+        }
+    }
+
+    /// Swap two entries of the dense array, without updating the sparse
+    /// array.
+    #[inline]
+    fn swap(&mut self, a: usize, b: usize) {
+        self.indices.swap(a, b);
+        self.elements.swap(a, b);
+    }
+
+    /// Get the sparse index of the next element, starting from the position
+    /// `cursor` in the dense array.
+    #[inline]
+    pub fn next_index(&self, cursor: &mut usize) -> Option<usize> {
+        let &index = self.indices.get(*cursor)?;
+        *cursor += 1;
+        Some(index as usize)
+    }
 }
 
-pub struct Iter<'a, T>(std::slice::Iter<'a, DenseEntry<T>>);
+pub struct Iter<'a, T>(std::slice::Iter<'a, T>);
+
+impl<'a, T> Iter<'a, T> {
+    #[inline]
+    pub(super) fn new(elements: &'a [T]) -> Self {
+        Self(elements.iter())
+    }
+}
 
 impl<'a, T> Iterator for Iter<'a, T> {
     type Item = &'a T;
 
     #[inline]
     fn next(&mut self) -> Option<Self::Item> {
-        self.0.next().map(|entry| &entry.element)
+        self.0.next()
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.0.size_hint()
     }
 }
 
-pub struct IterMut<'a, T>(std::slice::IterMut<'a, DenseEntry<T>>);
+impl<'a, T> ExactSizeIterator for Iter<'a, T> {}
+
+pub struct IterMut<'a, T>(std::slice::IterMut<'a, T>);
+
+impl<'a, T> IterMut<'a, T> {
+    #[inline]
+    pub(super) fn new(elements: &'a mut [T]) -> Self {
+        Self(elements.iter_mut())
+    }
+}
 
 impl<'a, T> Iterator for IterMut<'a, T> {
     type Item = &'a mut T;
 
     #[inline]
     fn next(&mut self) -> Option<Self::Item> {
-        self.0.next().map(|entry| &mut entry.element)
+        self.0.next()
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.0.size_hint()
     }
 }
 
+impl<'a, T> ExactSizeIterator for IterMut<'a, T> {}
+
 #[derive(Debug, Clone)]
 struct SparseArrayPage {
-    entries: Box<[Option<usize>; PAGE_SIZE]>,
+    entries: Box<[Option<NonMaxU32>; PAGE_SIZE]>,
+
+    /// The number of entries that are set, so empty pages can be freed.
+    len: usize,
 }
 
 impl Default for SparseArrayPage {
     fn default() -> Self {
         Self {
             entries: Box::new([None; PAGE_SIZE]),
+            len: 0,
         }
     }
 }
@@ -134,37 +291,53 @@ struct SparseArray {
 impl SparseArray {
     fn get(&self, index: usize) -> Option<usize> {
         let (page_index, offset) = page_index(index);
-        *self.pages.get(page_index)?.as_ref()?.entries.get(offset)?
+        let page = self.pages.get(page_index)?.as_ref()?;
+        page.entries[offset].map(NonMaxU32::get)
     }
 
     fn set(&mut self, index: usize, dense_index: usize) {
         let (page_index, offset) = page_index(index);
-        self.get_or_create_page(page_index).entries[offset] = Some(dense_index);
+        let page = self.get_or_create_page(page_index);
+        if page.entries[offset]
+            .replace(NonMaxU32::new(dense_index))
+            .is_none()
+        {
+            page.len += 1;
+        }
     }
 
     /// Panics if index isn't a valid entry.
     fn remove(&mut self, index: usize) {
         let (page_index, offset) = page_index(index);
 
-        let entries = &mut self
-            .pages
-            .get_mut(page_index)
-            .unwrap()
-            .as_mut()
-            .unwrap()
-            .entries;
+        let slot = self.pages.get_mut(page_index).unwrap();
+        let page = slot.as_mut().unwrap();
+        page.entries[offset] = None;
+        page.len -= 1;
+
+        if page.len == 0 {
+            *slot = None;
+        }
+    }
+
+    fn shrink_to_fit(&mut self) {
+        while let Some(None) = self.pages.last() {
+            self.pages.pop();
+        }
+        self.pages.shrink_to_fit();
+    }
 
-        entries[offset] = None;
+    fn allocated_bytes(&self) -> usize {
+        let pages = self.pages.iter().filter(|page| page.is_some()).count();
+        self.pages.capacity() * std::mem::size_of::<Option<SparseArrayPage>>()
+            + pages * std::mem::size_of::<[Option<NonMaxU32>; PAGE_SIZE]>()
     }
 
     fn get_or_create_page(&mut self, page_index: usize) -> &mut SparseArrayPage {
         if page_index >= self.pages.len() {
-            let none_pages = self.pages.len();
-            self.pages.reserve(none_pages + 1);
-            self.pages.extend(std::iter::repeat(None).take(none_pages));
-            self.pages.push(Some(SparseArrayPage::default()));
+            self.pages.resize(page_index + 1, None);
         }
-        self.pages[page_index].as_mut().unwrap()
+        self.pages[page_index].get_or_insert_with(SparseArrayPage::default)
     }
 }
 
@@ -0,0 +1,150 @@
+use super::sparse_set::{Iter, IterMut};
+
+/// Storage for zero-sized components (tags), which only records which
+/// entities have them.
+#[derive(Debug)]
+pub(crate) struct TagSet<T> {
+    /// A bit for each entity, set if it has the tag.
+    bits: Vec<u64>,
+
+    /// A value for each entity with the tag, so that tags can be borrowed and
+    /// moved out. Since tags are zero-sized, this never allocates.
+    tags: Vec<T>,
+}
+
+impl<T> Default for TagSet<T> {
+    fn default() -> Self {
+        debug_assert_eq!(std::mem::size_of::<T>(), 0, "tags must be zero-sized");
+
+        Self {
+            bits: vec![],
+            tags: vec![],
+        }
+    }
+}
+
+impl<T> TagSet<T> {
+    #[inline]
+    pub fn get(&self, index: usize) -> Option<&T> {
+        if self.contains(index) {
+            self.tags.first()
+        } else {
+            None
+        }
+    }
+
+    #[inline]
+    pub fn get_mut(&mut self, index: usize) -> Option<&mut T> {
+        if self.contains(index) {
+            self.tags.first_mut()
+        } else {
+            None
+        }
+    }
+
+    pub fn insert(&mut self, index: usize, tag: T) -> Option<T> {
+        if self.contains(index) {
+            return Some(std::mem::replace(&mut self.tags[0], tag));
+        }
+
+        let (word, bit) = (index / 64, index % 64);
+        if self.bits.len() <= word {
+            self.bits.resize(word + 1, 0);
+        }
+
+        self.bits[word] |= 1 << bit;
+        self.tags.push(tag);
+
+        None
+    }
+
+    pub fn remove(&mut self, index: usize) -> Option<T> {
+        if !self.contains(index) {
+            return None;
+        }
+
+        self.bits[index / 64] &= !(1 << (index % 64));
+        self.tags.pop()
+    }
+
+    #[inline]
+    pub fn iter(&self) -> Iter<'_, T> {
+        Iter::new(&self.tags)
+    }
+
+    #[inline]
+    pub fn iter_mut(&mut self) -> IterMut<'_, T> {
+        IterMut::new(&mut self.tags)
+    }
+
+    #[inline]
+    pub fn as_slice(&self) -> &[T] {
+        &self.tags
+    }
+
+    #[inline]
+    pub fn as_mut_slice(&mut self) -> &mut [T] {
+        &mut self.tags
+    }
+
+    /// The bits of the entities with the tag, and the tags.
+    #[inline]
+    pub fn split_mut(&mut self) -> (&[u64], &mut [T]) {
+        (&self.bits, &mut self.tags)
+    }
+
+    #[inline]
+    pub fn contains(&self, index: usize) -> bool {
+        self.bits
+            .get(index / 64)
+            .is_some_and(|word| word & (1 << (index % 64)) != 0)
+    }
+
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.tags.len()
+    }
+
+    pub fn shrink_to_fit(&mut self) {
+        while let Some(0) = self.bits.last() {
+            self.bits.pop();
+        }
+        self.bits.shrink_to_fit();
+    }
+
+    pub fn clear(&mut self) {
+        self.bits.clear();
+        self.tags.clear();
+    }
+
+    /// The number of bytes allocated by the set. Tags themselves take up no
+    /// space.
+    #[inline]
+    pub fn allocated_bytes(&self) -> usize {
+        self.bits.capacity() * std::mem::size_of::<u64>()
+    }
+
+    /// Get the index of the next entity with the tag, starting from the index
+    /// `cursor`.
+    #[inline]
+    pub fn next_index(&self, cursor: &mut usize) -> Option<usize> {
+        next_bit(&self.bits, cursor)
+    }
+}
+
+/// Get the index of the next set bit, starting from the index `cursor`.
+pub(super) fn next_bit(bits: &[u64], cursor: &mut usize) -> Option<usize> {
+    let mut word_index = *cursor / 64;
+
+    // Ignore the bits before the cursor in the first word.
+    let mut word = *bits.get(word_index)? & (u64::MAX << (*cursor % 64));
+
+    while word == 0 {
+        word_index += 1;
+        word = *bits.get(word_index)?;
+    }
+
+    let index = word_index * 64 + word.trailing_zeros() as usize;
+    *cursor = index + 1;
+    Some(index)
+}
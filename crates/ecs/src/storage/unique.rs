@@ -1,10 +1,43 @@
 use std::any::Any;
 
-use super::entities::LiveEntity;
-use super::StorageWithEntities;
+use super::entities::{EntityId, EntityMap, LiveEntity};
+use super::{MemoryUsage, StorageWithEntities};
+use crate::all_storages::AllStorages;
+use crate::commands::Commands;
 
-pub struct UniqueStorage<T: Any>(pub T);
+/// Storage for a unique, which is empty once the unique is removed.
+///
+/// Removing a unique leaves its (empty) storage in place, so that existing
+/// lookups stay valid.
+pub struct UniqueStorage<T: Any + Send + Sync>(pub Option<T>);
 
-impl<T: Any> StorageWithEntities for UniqueStorage<T> {
+impl<T: Any + Send + Sync> Default for UniqueStorage<T> {
+    fn default() -> Self {
+        Self(None)
+    }
+}
+
+impl<T: Any + Send + Sync> StorageWithEntities for UniqueStorage<T> {
     fn remove_entity(&mut self, _entity: &LiveEntity) {}
+
+    fn transfer_entity(
+        &mut self,
+        _entity: &LiveEntity,
+        _dest: &mut AllStorages,
+        _dest_entity: EntityId,
+        _entity_map: &EntityMap,
+    ) {
+    }
+
+    fn take_commands(&mut self, _commands: &mut Commands) {}
+
+    fn shrink_to_fit(&mut self) {}
+
+    fn memory_usage(&self) -> MemoryUsage {
+        MemoryUsage {
+            name: std::any::type_name::<T>(),
+            len: 0,
+            bytes: 0,
+        }
+    }
 }
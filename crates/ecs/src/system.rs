@@ -2,28 +2,109 @@ use paste::paste;
 
 use crate::prelude::*;
 
-pub trait System<'a, Params, Output: SystemOutput> {
-    fn run(&mut self, world: &'a mut World) -> SystemResult<Output::Success, Output::Error>;
+pub trait System<Params, Output: SystemOutput> {
+    #[cfg_attr(debug_assertions, track_caller)]
+    fn run(&mut self, world: &mut World) -> SystemResult<Output::Success, Output::Error>;
+
+    /// Whether the system can run on threads other than the main thread.
+    fn is_send(&self) -> bool;
+
+    /// The data borrowed by the system.
+    ///
+    /// Fails if the system's parameters conflict with each other.
+    fn access(&self) -> Result<Access, AccessConflict>;
+
+    /// Whether the system needs exclusive access to the world, and so has to
+    /// run on its own.
+    #[inline]
+    fn is_exclusive(&self) -> bool {
+        false
+    }
+
+    #[inline]
+    fn name(&self) -> &'static str {
+        std::any::type_name::<Self>()
+    }
 }
 
-pub trait Query<'a>: Sized {
-    type Index;
+/// Something that can be borrowed from the world, such as a component storage.
+///
+/// Queries are implemented for a type with any lifetime, and `Item` is that
+/// same type borrowed from a world.
+pub trait Query {
+    type Index: Clone;
+    type Item<'a>;
 
     fn lookup(world: &mut World) -> Self::Index;
-    fn borrow(world: &'a World, idx: Self::Index) -> BorrowResult<Self>;
+    #[cfg_attr(debug_assertions, track_caller)]
+    fn borrow<'a>(world: &'a World, idx: Self::Index) -> BorrowResult<Self::Item<'a>>;
+
+    /// Add the data borrowed by the query to `access`.
+    fn access(access: &mut Access) -> Result<(), AccessConflict>;
+
+    /// Whether the query can be used on threads other than the main thread.
+    #[inline]
+    fn is_send() -> bool {
+        true
+    }
 
-    fn lookup_and_borrow(world: &'a mut World) -> BorrowResult<Self> {
+    #[cfg_attr(debug_assertions, track_caller)]
+    fn lookup_and_borrow(world: &mut World) -> BorrowResult<Self::Item<'_>> {
         let idx = Self::lookup(world);
         Self::borrow(world, idx)
     }
 }
 
+/// Cached lookups for a query, so that it can be borrowed from a world
+/// repeatedly (e.g. inside an exclusive system).
+pub struct SystemState<Q: Query> {
+    idx: Q::Index,
+}
+
+impl<Q: Query> SystemState<Q> {
+    pub fn new(world: &mut World) -> Self {
+        Self {
+            idx: Q::lookup(world),
+        }
+    }
+
+    /// Borrow the query.
+    ///
+    /// Panics upon failure.
+    #[inline]
+    #[track_caller]
+    pub fn get<'a>(&self, world: &'a World) -> Q::Item<'a> {
+        match self.try_get(world) {
+            Ok(item) => item,
+            Err(err) => panic!("borrow error: {err}"),
+        }
+    }
+
+    /// Try to borrow the query.
+    #[inline]
+    #[cfg_attr(debug_assertions, track_caller)]
+    pub fn try_get<'a>(&self, world: &'a World) -> BorrowResult<Q::Item<'a>> {
+        Q::borrow(world, self.idx.clone())
+    }
+}
+
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub enum SystemError<Error> {
     BorrowError(BorrowError),
     ExecutionError(Error),
 }
 
+impl<Error> SystemError<Error> {
+    /// Map the execution error, leaving borrow errors unchanged.
+    #[inline]
+    pub fn map<F>(self, f: impl FnOnce(Error) -> F) -> SystemError<F> {
+        match self {
+            Self::BorrowError(err) => SystemError::BorrowError(err),
+            Self::ExecutionError(err) => SystemError::ExecutionError(f(err)),
+        }
+    }
+}
+
 impl<Error> From<BorrowError> for SystemError<Error> {
     fn from(err: BorrowError) -> Self {
         Self::BorrowError(err)
@@ -58,23 +139,229 @@ impl<S, E> SystemOutput for Result<S, E> {
     }
 }
 
+/// A system that takes an input value as its first parameter, wrapped in
+/// [`In`].
+pub trait InputSystem<Input, Params, Output: SystemOutput> {
+    #[cfg_attr(debug_assertions, track_caller)]
+    fn run_with(
+        &mut self,
+        input: Input,
+        world: &mut World,
+    ) -> SystemResult<Output::Success, Output::Error>;
+
+    /// Whether the system can run on threads other than the main thread.
+    fn is_send(&self) -> bool;
+
+    /// The data borrowed by the system.
+    ///
+    /// Fails if the system's parameters conflict with each other.
+    fn access(&self) -> Result<Access, AccessConflict>;
+
+    /// Whether the system needs exclusive access to the world, and so has to
+    /// run on its own.
+    #[inline]
+    fn is_exclusive(&self) -> bool {
+        false
+    }
+
+    #[inline]
+    fn name(&self) -> &'static str {
+        std::any::type_name::<Self>()
+    }
+}
+
+/// The input to an [`InputSystem`], such as the output of a piped system.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct In<T>(pub T);
+
+/// Marker for systems that take the world exclusively (`FnMut(&mut World)`).
+pub struct Exclusive;
+
+impl<Func, Output> System<Exclusive, Output> for Func
+where
+    Func: FnMut(&mut World) -> Output,
+    Output: SystemOutput,
+{
+    #[inline]
+    fn run(&mut self, world: &mut World) -> SystemResult<Output::Success, Output::Error> {
+        (self)(world).to_result()
+    }
+
+    #[inline]
+    fn is_send(&self) -> bool {
+        false
+    }
+
+    #[inline]
+    fn access(&self) -> Result<Access, AccessConflict> {
+        Ok(Access::world())
+    }
+
+    #[inline]
+    fn is_exclusive(&self) -> bool {
+        true
+    }
+}
+
+impl<Func, Input, Output> InputSystem<Input, Exclusive, Output> for Func
+where
+    Func: FnMut(In<Input>, &mut World) -> Output,
+    Output: SystemOutput,
+{
+    #[inline]
+    fn run_with(
+        &mut self,
+        input: Input,
+        world: &mut World,
+    ) -> SystemResult<Output::Success, Output::Error> {
+        (self)(In(input), world).to_result()
+    }
+
+    #[inline]
+    fn is_send(&self) -> bool {
+        false
+    }
+
+    #[inline]
+    fn access(&self) -> Result<Access, AccessConflict> {
+        Ok(Access::world())
+    }
+
+    #[inline]
+    fn is_exclusive(&self) -> bool {
+        true
+    }
+}
+
+macro_rules! impl_query_tuple {
+    ($($param:ident),*) => {
+        impl<$($param: Query),*> Query for ($($param,)*) {
+            type Index = ($($param::Index,)*);
+            type Item<'a> = ($($param::Item<'a>,)*);
+
+            #[inline]
+            #[allow(unused_variables, clippy::unused_unit)]
+            fn lookup(world: &mut World) -> Self::Index {
+                ($($param::lookup(world),)*)
+            }
+
+            #[inline]
+            #[allow(unused_variables, non_snake_case)]
+            fn borrow<'a>(world: &'a World, idx: Self::Index) -> BorrowResult<Self::Item<'a>> {
+                let ($($param,)*) = idx;
+                Ok(($($param::borrow(world, $param)?,)*))
+            }
+
+            #[inline]
+            #[allow(unused_variables)]
+            fn access(access: &mut Access) -> Result<(), AccessConflict> {
+                $($param::access(access)?;)*
+                Ok(())
+            }
+
+            #[inline]
+            fn is_send() -> bool {
+                true $(&& $param::is_send())*
+            }
+        }
+    };
+}
+
+// The function has to be called through a function taking the borrowed
+// queries, otherwise rustc can't tell which `FnMut` implementation to use.
 macro_rules! impl_system {
     ($($param:ident),*) => {
-        impl<'a, Func, Output, $($param: Query<'a>),*>
-        System<'a, ($($param,)*), Output>
+        impl<Func, Output, $($param: Query),*>
+        System<($($param,)*), Output>
         for Func
-        where Func: FnMut($($param),*) -> Output, Output: SystemOutput
+        where
+            for<'a> &'a mut Func:
+                FnMut($($param),*) -> Output
+                + FnMut($($param::Item<'_>),*) -> Output,
+            Output: SystemOutput,
         {
             #[allow(unused_variables, non_snake_case)]
-            fn run(&mut self, world: &'a mut World) -> SystemResult<Output::Success, Output::Error> {
+            fn run(&mut self, world: &mut World) -> SystemResult<Output::Success, Output::Error> {
+                #[allow(clippy::too_many_arguments)]
+                fn call<Output, $($param),*>(
+                    mut func: impl FnMut($($param),*) -> Output,
+                    $($param: $param),*
+                ) -> Output {
+                    func($($param),*)
+                }
+
                 $(let paste!([<$param _idx>]) = $param::lookup(world);)*
                 $(let $param = $param::borrow(world, paste!([<$param _idx>]))?;)*
-                (self)($($param,)*).to_result()
+                call(self, $($param),*).to_result()
+            }
+
+            #[inline]
+            fn is_send(&self) -> bool {
+                true $(&& $param::is_send())*
+            }
+
+            #[inline]
+            fn access(&self) -> Result<Access, AccessConflict> {
+                let mut access = Access::default();
+                <($($param,)*)>::access(&mut access)?;
+                Ok(access)
+            }
+        }
+
+        impl<Func, Input, Output, $($param: Query),*>
+        InputSystem<Input, ($($param,)*), Output>
+        for Func
+        where
+            for<'a> &'a mut Func:
+                FnMut(In<Input>, $($param),*) -> Output
+                + FnMut(In<Input>, $($param::Item<'_>),*) -> Output,
+            Output: SystemOutput,
+        {
+            #[allow(unused_variables, non_snake_case)]
+            fn run_with(
+                &mut self,
+                input: Input,
+                world: &mut World,
+            ) -> SystemResult<Output::Success, Output::Error> {
+                #[allow(clippy::too_many_arguments)]
+                fn call<Input, Output, $($param),*>(
+                    mut func: impl FnMut(In<Input>, $($param),*) -> Output,
+                    input: In<Input>,
+                    $($param: $param),*
+                ) -> Output {
+                    func(input, $($param),*)
+                }
+
+                $(let paste!([<$param _idx>]) = $param::lookup(world);)*
+                $(let $param = $param::borrow(world, paste!([<$param _idx>]))?;)*
+                call(self, In(input), $($param),*).to_result()
+            }
+
+            #[inline]
+            fn is_send(&self) -> bool {
+                true $(&& $param::is_send())*
+            }
+
+            #[inline]
+            fn access(&self) -> Result<Access, AccessConflict> {
+                let mut access = Access::default();
+                <($($param,)*)>::access(&mut access)?;
+                Ok(access)
             }
         }
     };
 }
 
+impl_query_tuple!();
+impl_query_tuple!(Q0);
+impl_query_tuple!(Q0, Q1);
+impl_query_tuple!(Q0, Q1, Q2);
+impl_query_tuple!(Q0, Q1, Q2, Q3);
+impl_query_tuple!(Q0, Q1, Q2, Q3, Q4);
+impl_query_tuple!(Q0, Q1, Q2, Q3, Q4, Q5);
+impl_query_tuple!(Q0, Q1, Q2, Q3, Q4, Q5, Q6);
+impl_query_tuple!(Q0, Q1, Q2, Q3, Q4, Q5, Q6, Q7);
+
 impl_system!();
 impl_system!(Q0);
 impl_system!(Q0, Q1);
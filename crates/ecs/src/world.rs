@@ -12,41 +12,298 @@ pub struct World {
 impl World {
     /// Spawn a new entity and create a handle for it.
     #[inline]
-    pub fn spawn(&mut self) -> EntityMut {
+    pub fn spawn(&mut self) -> EntityMut<'_> {
+        self.flush();
         self.all_storages.spawn()
     }
 
+    /// Limit entity versions to `bits` bits, and set what happens when an
+    /// entity index runs out of versions. By default versions have 32 bits,
+    /// and indices are retired once they run out.
+    ///
+    /// Panics if `bits` isn't between 1 and 32, or any entities have been
+    /// spawned or reserved.
+    pub fn set_entity_generations(&mut self, bits: u32, on_overflow: GenerationOverflow) {
+        self.all_storages
+            .entities
+            .set_generations(bits, on_overflow);
+    }
+
+    /// How many times an entity's version has wrapped back to 0, under
+    /// [`GenerationOverflow::Wrap`]. Each wrap risks stale ids matching a
+    /// new entity.
+    #[inline]
+    pub fn generation_wraps(&self) -> usize {
+        self.all_storages.entities.generation_wraps()
+    }
+
+    /// Call `hook` whenever an entity's version wraps back to 0 under
+    /// [`GenerationOverflow::Wrap`], e.g. to log a warning. It's passed the
+    /// id of the entity that was despawned.
+    pub fn on_generation_wrap(&mut self, hook: impl FnMut(EntityId) + Send + Sync + 'static) {
+        self.all_storages.entities.on_generation_wrap(hook);
+    }
+
+    /// Reserve an entity id without needing exclusive access to the world.
+    ///
+    /// The id can be used straight away (e.g. stored in components), but the
+    /// entity itself only exists once the world is flushed.
+    #[inline]
+    pub fn reserve_entity(&self) -> EntityId {
+        self.all_storages.entities.reserve()
+    }
+
+    /// Apply any deferred operations, such as spawning reserved entities and
+    /// applying commands queued by component hooks.
+    ///
+    /// This is done automatically when the world is next accessed mutably.
+    ///
+    /// Panics if any storage is borrowed.
+    pub fn flush(&mut self) {
+        self.all_storages.entities.flush();
+
+        // Applying commands may queue more commands.
+        loop {
+            let commands = self.all_storages.take_commands();
+            if commands.is_empty() {
+                break;
+            }
+            commands.apply(self);
+        }
+    }
+
     /// Get a handle for an entity.
     #[inline]
-    pub fn entity(&mut self, entity: EntityId) -> EntityMut {
+    pub fn entity(&mut self, entity: EntityId) -> EntityMut<'_> {
+        self.flush();
         self.all_storages.entity(entity)
     }
 
+    /// Get a read-only handle for an entity.
+    ///
+    /// Unlike [`World::entity`], this can't flush the world, so reserved
+    /// entities can't be read until the world has been flushed.
+    ///
+    /// Panics if the entity is dead.
+    #[inline]
+    pub fn entity_ref(&self, entity: EntityId) -> EntityRef<'_> {
+        if !self.all_storages.entities.is_alive(entity) {
+            panic!("entity {entity:?} is dead");
+        }
+
+        EntityRef::new(entity, &self.all_storages)
+    }
+
+    /// Move an entity and all of its components into another world.
+    ///
+    /// Returns the entity's id in the other world. Components are given a
+    /// chance to remap any references to the moved entity.
+    ///
+    /// Returns an error without moving anything if the entity ran out of
+    /// versions under [`GenerationOverflow::Error`].
+    ///
+    /// Panics if the entity is dead or any storage is borrowed.
+    pub fn transfer(
+        &mut self,
+        entity: EntityId,
+        other: &mut World,
+    ) -> Result<EntityId, GenerationOverflowError> {
+        self.flush();
+
+        if !self.all_storages.entities.is_alive(entity) {
+            panic!("entity {entity:?} is dead");
+        }
+        self.all_storages.entities.check_despawn(entity)?;
+
+        let new_entity = other.all_storages.entities.spawn();
+
+        let mut entity_map = EntityMap::default();
+        entity_map.insert(entity, new_entity);
+
+        self.all_storages
+            .transfer_entities(&mut other.all_storages, &entity_map);
+
+        Ok(new_entity)
+    }
+
+    /// Move all entities from another world into this one, leaving the
+    /// other world without any entities.
+    ///
+    /// Returns a mapping from the old entity ids to the new ones, which is
+    /// also used to remap entity ids held by components. Uniques are not
+    /// moved.
+    ///
+    /// Returns an error without moving anything if any entity in the other
+    /// world ran out of versions under [`GenerationOverflow::Error`].
+    ///
+    /// Panics if any storage in either world is borrowed.
+    pub fn merge(&mut self, other: &mut World) -> Result<EntityMap, GenerationOverflowError> {
+        other.flush();
+
+        let entities = other.all_storages.entities.alive();
+        for &entity in &entities {
+            other.all_storages.entities.check_despawn(entity)?;
+        }
+
+        let mut entity_map = EntityMap::default();
+        for entity in entities {
+            entity_map.insert(entity, self.all_storages.entities.spawn());
+        }
+
+        other
+            .all_storages
+            .transfer_entities(&mut self.all_storages, &entity_map);
+
+        Ok(entity_map)
+    }
+
+    /// Despawn every entity.
+    ///
+    /// Component types, hooks and uniques are kept. Component hooks run as
+    /// if each entity was despawned, and their commands are applied on the
+    /// next flush.
+    ///
+    /// Returns an error without despawning anything if any entity ran out of
+    /// versions under [`GenerationOverflow::Error`].
+    ///
+    /// Panics if any storage is borrowed.
+    pub fn clear(&mut self) -> Result<(), GenerationOverflowError> {
+        self.flush();
+        self.all_storages.clear()
+    }
+
+    /// Reserve room for at least `additional` more entities.
     #[inline]
-    pub fn insert_unique<T: Any>(&mut self, unique: T) -> Option<()> {
+    pub fn reserve_entities(&mut self, additional: usize) {
+        self.all_storages.entities.reserve_capacity(additional);
+    }
+
+    /// Release memory that isn't being used by any storage, such as after
+    /// a spike in the number of entities.
+    ///
+    /// Panics if any storage is borrowed.
+    pub fn shrink_to_fit(&mut self) {
+        self.flush();
+        self.all_storages.shrink_to_fit();
+    }
+
+    /// Report how much memory each storage is using, starting with the
+    /// entities themselves.
+    ///
+    /// Panics if any storage is borrowed mutably.
+    pub fn memory_usage(&self) -> Vec<MemoryUsage> {
+        self.all_storages.memory_usage()
+    }
+
+    /// Get the lifecycle hooks for a component type, to register new ones.
+    pub fn hooks<C: Component>(&mut self) -> &mut ComponentHooks<C> {
+        let idx = self.all_storages.register_component::<C>();
+        self.all_storages.components.get_mut(idx).hooks_mut()
+    }
+
+    /// Insert a unique.
+    ///
+    /// Returns `None` if the unique already exists, in which case it is left
+    /// unchanged.
+    #[inline]
+    pub fn insert_unique<T: Any + Send + Sync>(&mut self, unique: T) -> Option<()> {
         self.all_storages.uniques.insert(unique)
     }
 
+    /// Insert a unique, replacing and returning any existing value.
+    #[inline]
+    pub fn replace_unique<T: Any + Send + Sync>(&mut self, unique: T) -> Option<T> {
+        self.all_storages.uniques.replace(unique)
+    }
+
+    /// Insert the default value of a unique if it doesn't already exist.
+    #[inline]
+    pub fn init_unique<T: Any + Send + Sync + Default>(&mut self) {
+        self.all_storages.uniques.get_or_insert_with(T::default);
+    }
+
+    /// Get a unique, inserting it with `f` if it doesn't already exist.
+    #[inline]
+    pub fn get_or_insert_with<T: Any + Send + Sync>(&mut self, f: impl FnOnce() -> T) -> &mut T {
+        self.all_storages.uniques.get_or_insert_with(f)
+    }
+
+    /// Remove a unique, returning it if it existed.
+    #[inline]
+    pub fn remove_unique<T: Any + Send + Sync>(&mut self) -> Option<T> {
+        self.all_storages.uniques.remove()
+    }
+
+    /// Insert a unique that isn't `Send` or `Sync`.
+    ///
+    /// Non-send uniques are accessed with [`NonSend`] and [`NonSendMut`], and
+    /// belong to the thread the world was created on. Borrowing them on
+    /// another thread fails, and inserting or removing them there panics, as
+    /// does dropping the world there while it holds any.
+    ///
+    /// Returns `None` if the non-send unique already exists, in which case it
+    /// is left unchanged.
+    #[inline]
+    pub fn insert_non_send<T: Any>(&mut self, unique: T) -> Option<()> {
+        self.all_storages.non_send.insert(unique)
+    }
+
+    /// Insert a non-send unique, replacing and returning any existing value.
+    #[inline]
+    pub fn replace_non_send<T: Any>(&mut self, unique: T) -> Option<T> {
+        self.all_storages.non_send.replace(unique)
+    }
+
+    /// Insert the default value of a non-send unique if it doesn't already exist.
+    #[inline]
+    pub fn init_non_send<T: Any + Default>(&mut self) {
+        self.all_storages.non_send.get_or_insert_with(T::default);
+    }
+
+    /// Get a non-send unique, inserting it with `f` if it doesn't already exist.
+    #[inline]
+    pub fn get_or_insert_non_send_with<T: Any>(&mut self, f: impl FnOnce() -> T) -> &mut T {
+        self.all_storages.non_send.get_or_insert_with(f)
+    }
+
+    /// Remove a non-send unique, returning it if it existed.
+    #[inline]
+    pub fn remove_non_send<T: Any>(&mut self) -> Option<T> {
+        self.all_storages.non_send.remove()
+    }
+
     /// Get a query.
     ///
     /// Panics upon failure.
     #[inline]
-    pub fn get<'a, P: Query<'a>>(&'a mut self) -> P {
-        P::lookup_and_borrow(self).unwrap_or_else(|err| {
-            panic!("borrow error: {err:?}");
-        })
+    #[track_caller]
+    pub fn get<P: Query>(&mut self) -> P::Item<'_> {
+        self.flush();
+        match P::lookup_and_borrow(self) {
+            Ok(item) => item,
+            Err(err) => panic!("borrow error: {err}"),
+        }
     }
 
     /// Try to get a query.
-    pub fn try_get<'a, P: Query<'a>>(&'a mut self) -> BorrowResult<P> {
+    #[cfg_attr(debug_assertions, track_caller)]
+    pub fn try_get<P: Query>(&mut self) -> BorrowResult<P::Item<'_>> {
+        self.flush();
         P::lookup_and_borrow(self)
     }
 
     /// Run a system.
-    pub fn run<'a, S: System<'a, Params, Output>, Params, Output: SystemOutput>(
-        &'a mut self,
+    ///
+    /// Fails without running the system if its parameters conflict with each
+    /// other.
+    #[cfg_attr(debug_assertions, track_caller)]
+    pub fn run<S: System<Params, Output>, Params, Output: SystemOutput>(
+        &mut self,
         mut system: S,
     ) -> SystemResult<Output::Success, Output::Error> {
+        system.access().map_err(BorrowError::from)?;
+
+        self.flush();
         system.run(self)
     }
 }
@@ -0,0 +1,184 @@
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use ecs::prelude::*;
+
+#[derive(Component, Debug, Default, PartialEq)]
+struct Transform(f32);
+
+#[derive(Component, Debug, Default, PartialEq)]
+struct Velocity(f32);
+
+static ADDED: AtomicUsize = AtomicUsize::new(0);
+static REMOVED: AtomicUsize = AtomicUsize::new(0);
+
+#[derive(Component)]
+#[component(
+    on_add = |_, _, _| { ADDED.fetch_add(1, Ordering::Relaxed); },
+    on_remove = count_removed,
+    require(Transform, Velocity = || Velocity(1.0)),
+)]
+struct RigidBody;
+
+fn count_removed(_entity: EntityId, _body: &RigidBody, _commands: &mut Commands) {
+    REMOVED.fetch_add(1, Ordering::Relaxed);
+}
+
+#[derive(Component, Debug, PartialEq)]
+#[component(storage = "sparse_set")]
+struct Marker;
+
+#[derive(Component)]
+#[component(storage = "tags")]
+struct Tag;
+
+#[test]
+fn derive_component() {
+    assert_eq!(Transform::STORAGE, StorageKind::Auto);
+    assert_eq!(Marker::STORAGE, StorageKind::SparseSet);
+    assert_eq!(Tag::STORAGE, StorageKind::Tags);
+
+    let mut world = World::default();
+
+    let a = world.spawn().insert(RigidBody).insert(Marker).id();
+    let b = world.spawn().insert(Transform(5.0)).insert(RigidBody).id();
+    world.spawn().insert(Tag);
+
+    assert_eq!(world.get::<Comp<Transform>>().get(a), Some(&Transform(0.0)));
+    assert_eq!(world.get::<Comp<Transform>>().get(b), Some(&Transform(5.0)));
+    assert_eq!(world.get::<Comp<Velocity>>().get(b), Some(&Velocity(1.0)));
+    assert_eq!(world.get::<Comp<Marker>>().as_slice(), [Marker]);
+
+    world.entity(b).despawn().unwrap();
+    assert_eq!(ADDED.load(Ordering::Relaxed), 2);
+    assert_eq!(REMOVED.load(Ordering::Relaxed), 1);
+}
+
+#[derive(Component, Debug, PartialEq)]
+struct Health(u32);
+
+#[derive(Component, Debug, PartialEq)]
+struct Name(&'static str);
+
+#[derive(Bundle)]
+struct Body {
+    transform: Transform,
+    velocity: Velocity,
+}
+
+#[derive(Bundle)]
+struct Player {
+    name: Name,
+    health: Health,
+    #[bundle]
+    body: Body,
+}
+
+#[test]
+fn derive_bundle() {
+    let mut world = World::default();
+
+    let player = Player {
+        name: Name("player"),
+        health: Health(10),
+        body: Body {
+            transform: Transform(1.0),
+            velocity: Velocity(2.0),
+        },
+    };
+    let a = world.spawn().insert_bundle(player).id();
+    let b = world
+        .spawn()
+        .insert_bundle((Transform(3.0), Health(5)))
+        .id();
+
+    assert_eq!(world.get::<Comp<Name>>().get(a), Some(&Name("player")));
+    assert_eq!(world.get::<Comp<Velocity>>().get(a), Some(&Velocity(2.0)));
+    assert_eq!(world.get::<Comp<Health>>().get(b), Some(&Health(5)));
+
+    world.entity(a).remove_bundle::<Body>();
+    assert_eq!(world.entity_ref(a).components().count(), 2);
+
+    world.entity(a).remove_bundle::<Player>();
+    world.entity(b).remove_bundle::<(Transform, Health)>();
+    assert_eq!(world.entity_ref(a).components().count(), 0);
+    assert_eq!(world.entity_ref(b).components().count(), 0);
+}
+
+macro_rules! counters {
+    ($($counter:ident),*) => {
+        $(
+            #[derive(Component, Default)]
+            struct $counter(u32);
+        )*
+    };
+}
+
+counters!(C0, C1, C2, C3, C4, C5, C6, C7, C8);
+
+// More fields than a system can take parameters.
+#[derive(SystemParam)]
+struct Counters<'a> {
+    c0: CompMut<'a, C0>,
+    c1: CompMut<'a, C1>,
+    c2: CompMut<'a, C2>,
+    c3: CompMut<'a, C3>,
+    c4: CompMut<'a, C4>,
+    c5: CompMut<'a, C5>,
+    c6: CompMut<'a, C6>,
+    c7: CompMut<'a, C7>,
+    c8: CompMut<'a, C8>,
+}
+
+impl Counters<'_> {
+    fn total(&self) -> u32 {
+        fn sum<C: Component>(counters: &CompMut<C>, count: impl Fn(&C) -> u32) -> u32 {
+            counters.iter().map(count).sum()
+        }
+
+        sum(&self.c0, |c| c.0)
+            + sum(&self.c1, |c| c.0)
+            + sum(&self.c2, |c| c.0)
+            + sum(&self.c3, |c| c.0)
+            + sum(&self.c4, |c| c.0)
+            + sum(&self.c5, |c| c.0)
+            + sum(&self.c6, |c| c.0)
+            + sum(&self.c7, |c| c.0)
+            + sum(&self.c8, |c| c.0)
+    }
+}
+
+#[derive(SystemParam)]
+struct Stats<'a, C: Component>(Comp<'a, C>, Option<Unique<'a, u32>>);
+
+#[test]
+fn derive_system_param() {
+    let mut world = World::default();
+
+    let entity = world
+        .spawn()
+        .insert_bundle((C0(0), C1(1), C2(2), C3(3), C4(4), C5(5), C6(6), C7(7)))
+        .insert(C8(8))
+        .id();
+
+    world
+        .run(|mut counters: Counters| {
+            assert_eq!(counters.total(), 36);
+            counters.c0.get_mut(entity).unwrap().0 += 10;
+            counters.c8.get_mut(entity).unwrap().0 += 10;
+        })
+        .unwrap();
+
+    let state = SystemState::<Stats<C8>>::new(&mut world);
+    let Stats(c8, unique) = state.get(&world);
+    assert_eq!(c8.iter().next().map(|c| c.0), Some(18));
+    assert!(unique.is_none());
+    drop((c8, unique));
+
+    assert_eq!(world.get::<Comp<C0>>().iter().next().map(|c| c.0), Some(10));
+
+    let result = world.run(|_counters: Counters, _c0: Comp<C0>| {});
+    assert!(matches!(
+        result,
+        Err(SystemError::BorrowError(BorrowError::AccessConflict(_)))
+    ));
+}
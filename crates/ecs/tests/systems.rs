@@ -0,0 +1,537 @@
+use ecs::prelude::*;
+
+#[derive(Debug, PartialEq, Eq)]
+struct Level(usize);
+impl Component for Level {}
+
+#[derive(Debug, PartialEq, Eq)]
+struct Enemy(usize);
+impl Component for Enemy {}
+
+fn count_enemies(enemies: Comp<Enemy>, mut count: UniqueMut<usize>) {
+    *count = enemies.iter().count();
+}
+
+fn load_level(world: &mut World) {
+    for id in 0..3 {
+        world.spawn().insert(Enemy(id));
+    }
+
+    let state = SystemState::<(Comp<Enemy>, UniqueMut<usize>)>::new(world);
+    let (enemies, mut loaded) = state.get(world);
+    *loaded += enemies.iter().count();
+}
+
+#[test]
+fn exclusive_system() {
+    let mut world = World::default();
+    world.insert_unique(0usize).unwrap();
+
+    world.run(load_level).unwrap();
+    assert_eq!(*world.get::<Unique<usize>>(), 3);
+}
+
+#[test]
+fn mixed_schedule() {
+    let mut world = World::default();
+    world.insert_unique(0usize).unwrap();
+
+    let mut schedule = Schedule::new();
+    schedule.add_system(load_level);
+    schedule.add_system(|world: &mut World| {
+        world.spawn().insert(Level(1));
+    });
+    schedule.add_system(count_enemies);
+
+    schedule.run(&mut world).unwrap();
+    assert_eq!(*world.get::<Unique<usize>>(), 3);
+
+    schedule.run(&mut world).unwrap();
+    assert_eq!(*world.get::<Unique<usize>>(), 6);
+    assert_eq!(world.get::<Comp<Level>>().iter().count(), 2);
+}
+
+#[test]
+fn schedule_failure() {
+    let mut world = World::default();
+    world.insert_unique(0usize).unwrap();
+
+    let mut schedule = Schedule::new();
+    schedule.add_system(|| -> Result<(), &'static str> { Err("oh no") });
+    schedule.add_system(|mut count: UniqueMut<usize>| *count += 1);
+
+    let failure = schedule.run(&mut world).unwrap_err();
+    assert!(matches!(failure.error, SystemError::ExecutionError(_)));
+
+    // The second system never ran.
+    assert_eq!(*world.get::<Unique<usize>>(), 0);
+}
+
+fn strongest_enemy(enemies: Comp<Enemy>) -> Result<usize, String> {
+    enemies
+        .iter()
+        .map(|enemy| enemy.0)
+        .max()
+        .ok_or_else(|| "no enemies".to_string())
+}
+
+fn set_difficulty(In(difficulty): In<usize>, mut current: UniqueMut<usize>) -> Result<(), String> {
+    if difficulty > 10 {
+        return Err("too difficult".to_string());
+    }
+
+    *current = difficulty;
+    Ok(())
+}
+
+#[test]
+fn pipe_systems() {
+    let mut world = World::default();
+    world.spawn().insert(Enemy(2));
+    world.spawn().insert(Enemy(5));
+
+    world.insert_unique(0usize).unwrap();
+
+    world.run(strongest_enemy.pipe(set_difficulty)).unwrap();
+    assert_eq!(*world.get::<Unique<usize>>(), 5);
+
+    world.spawn().insert(Enemy(20));
+    let err = world.run(strongest_enemy.pipe(set_difficulty)).unwrap_err();
+    assert_eq!(
+        err,
+        SystemError::ExecutionError("too difficult".to_string())
+    );
+    assert_eq!(*world.get::<Unique<usize>>(), 5);
+}
+
+#[test]
+fn adapt_system_output() {
+    let mut world = World::default();
+    world.insert_unique(0usize).unwrap();
+
+    // The first system fails, so the second never runs.
+    let err = world.run(strongest_enemy.pipe(set_difficulty)).unwrap_err();
+    assert_eq!(err, SystemError::ExecutionError("no enemies".to_string()));
+
+    let err = world
+        .run(strongest_enemy.map_err(|err| err.len()))
+        .unwrap_err();
+    assert_eq!(err, SystemError::ExecutionError(10));
+
+    assert_eq!(world.run(strongest_enemy.unwrap_or_default()), Ok(0));
+    assert_eq!(world.run(strongest_enemy.ignore()), Ok(()));
+
+    // Borrow errors aren't swallowed.
+    let err = world
+        .run((|_: Unique<bool>| -> Result<(), ()> { Err(()) }).ignore())
+        .unwrap_err();
+    assert!(matches!(err, SystemError::BorrowError(_)));
+
+    // Adapters are named after the system they wrap.
+    let name = System::name(&strongest_enemy);
+    assert!(name.ends_with("strongest_enemy"));
+    assert_eq!(strongest_enemy.ignore().name(), name);
+
+    // Pipes are named after both systems.
+    let piped = strongest_enemy.pipe(set_difficulty);
+    assert_eq!(
+        piped.name(),
+        format!("{name} | {}", InputSystem::name(&set_difficulty))
+    );
+    assert!(!piped.is_exclusive());
+
+    let piped = strongest_enemy.pipe(
+        |In(difficulty): In<usize>, world: &mut World| -> Result<(), String> {
+            world.insert_unique(difficulty).unwrap();
+            Ok(())
+        },
+    );
+    assert!(piped.is_exclusive());
+    assert!(!piped.is_send());
+}
+
+fn fail() -> Result<(), &'static str> {
+    Err("oh no")
+}
+
+fn increment(mut count: UniqueMut<usize>) {
+    *count += 1;
+}
+
+#[test]
+fn schedule_error_policies() {
+    let mut world = World::default();
+    world.insert_unique(0usize).unwrap();
+
+    let logged = std::rc::Rc::new(std::cell::Cell::new(0));
+    let mut schedule = Schedule::new();
+    schedule.on_error(ErrorPolicy::LogAndContinue).log_with({
+        let logged = logged.clone();
+        move |failure| {
+            assert!(failure.system.ends_with("fail"));
+            logged.set(logged.get() + 1);
+        }
+    });
+
+    let failing = schedule.add_system_with(
+        fail,
+        SystemConfig::new().on_error(ErrorPolicy::SkipDependants),
+    );
+    let skipped = schedule.add_system_with(increment, SystemConfig::new().after(failing));
+    schedule.add_system_with(increment, SystemConfig::new().after(skipped));
+    schedule.add_system(fail);
+    schedule.add_system(increment);
+
+    let report = schedule.run(&mut world).unwrap();
+    assert!(!report.is_ok());
+    assert_eq!(report.failures.len(), 2);
+    assert!(report
+        .failures
+        .iter()
+        .all(|failure| failure.system.ends_with("fail")));
+    assert_eq!(report.skipped.len(), 2);
+
+    // Only the failure under the schedule's policy was logged.
+    assert_eq!(logged.get(), 1);
+
+    // Only the last system ran.
+    assert_eq!(*world.get::<Unique<usize>>(), 1);
+}
+
+#[test]
+fn schedule_retry_next_frame() {
+    let mut world = World::default();
+    world.insert_unique(0usize).unwrap();
+
+    let ready = std::rc::Rc::new(std::cell::Cell::new(false));
+    let attempts = std::rc::Rc::new(std::cell::Cell::new(0));
+
+    let mut schedule = Schedule::new();
+    let flaky = schedule.add_system_with(
+        {
+            let ready = ready.clone();
+            let attempts = attempts.clone();
+            move || {
+                attempts.set(attempts.get() + 1);
+                if ready.get() {
+                    Ok(())
+                } else {
+                    Err("not ready")
+                }
+            }
+        },
+        SystemConfig::new().on_error(ErrorPolicy::RetryNextFrame),
+    );
+    schedule.add_system_with(increment, SystemConfig::new().after(flaky));
+
+    // The first failure isn't reported, and the system is retried first
+    // thing next run.
+    let report = schedule.run(&mut world).unwrap();
+    assert!(report.failures.is_empty());
+    assert_eq!(report.retrying.len(), 1);
+    assert_eq!(report.skipped.len(), 1);
+    assert_eq!(attempts.get(), 1);
+
+    // The retry fails too, so it's reported and the system's dependants are
+    // skipped without running it again.
+    let report = schedule.run(&mut world).unwrap();
+    assert_eq!(report.failures.len(), 1);
+    assert!(report.retrying.is_empty());
+    assert_eq!(report.skipped.len(), 1);
+    assert_eq!(attempts.get(), 2);
+
+    schedule.run(&mut world).unwrap();
+    assert_eq!(attempts.get(), 3);
+
+    // The retry succeeds, and the system runs again in its usual place.
+    ready.set(true);
+    let report = schedule.run(&mut world).unwrap();
+    assert!(report.is_ok());
+    assert_eq!(attempts.get(), 5);
+    assert_eq!(*world.get::<Unique<usize>>(), 1);
+}
+
+#[test]
+#[should_panic(expected = "oh no")]
+fn schedule_panic_policy() {
+    let mut world = World::default();
+
+    let mut schedule = Schedule::new();
+    schedule.on_error(ErrorPolicy::Panic).add_system(fail);
+    let _ = schedule.run(&mut world);
+}
+
+#[test]
+fn conflicting_system_access() {
+    let mut world = World::default();
+    world.spawn().insert(Enemy(0));
+
+    let system = |_: Comp<Enemy>, _: CompMut<Enemy>| {};
+    let conflict = system.access().unwrap_err();
+    assert!(conflict.type_name.ends_with("Enemy"));
+
+    assert_eq!(
+        world.run(system),
+        Err(SystemError::BorrowError(BorrowError::AccessConflict(
+            conflict
+        )))
+    );
+
+    // Reading the same data twice is fine.
+    world.run(|_: Comp<Enemy>, _: Comp<Enemy>| {}).unwrap();
+}
+
+#[test]
+#[should_panic(expected = "conflicting access")]
+fn schedule_rejects_conflicting_system() {
+    let mut schedule = Schedule::new();
+    schedule.add_system(|_: UniqueMut<usize>, _: Option<Unique<usize>>| {});
+}
+
+#[test]
+fn schedule_ambiguities() {
+    let mut schedule = Schedule::new();
+
+    let count = schedule.add_system(count_enemies);
+    schedule.add_system_with(increment, SystemConfig::new().after(count));
+    schedule.add_system(|_: Comp<Enemy>, _: Comp<Level>| {});
+    schedule.add_system(|_: CompMut<Level>| {});
+
+    let ambiguities = schedule.ambiguities();
+    assert_eq!(ambiguities.len(), 1);
+    assert_eq!(ambiguities[0].conflicts.len(), 1);
+    assert!(ambiguities[0].conflicts[0].ends_with("Level"));
+
+    // Exclusive systems conflict with everything.
+    schedule.add_system(load_level);
+    assert_eq!(schedule.ambiguities().len(), 5);
+}
+
+#[derive(Debug, PartialEq)]
+struct Position(f32);
+impl Component for Position {}
+
+#[derive(Debug, PartialEq)]
+struct Speed(f32);
+impl Component for Speed {}
+
+struct Frozen;
+impl Component for Frozen {}
+
+#[test]
+fn join_components() {
+    let mut world = World::default();
+
+    let a = world.spawn().insert(Position(0.0)).insert(Speed(1.0)).id();
+    let b = world.spawn().insert(Position(0.0)).id();
+    let c = world
+        .spawn()
+        .insert(Position(0.0))
+        .insert(Speed(2.0))
+        .insert(Frozen)
+        .id();
+    let d = world.spawn().insert(Speed(3.0)).insert(Enemy(0)).id();
+
+    world
+        .run(
+            |mut positions: CompMut<Position>, speeds: Comp<Speed>, frozen: Without<Frozen>| {
+                (&mut positions, &speeds, &frozen)
+                    .join()
+                    .for_each(|(position, speed, ())| position.0 += speed.0);
+            },
+        )
+        .unwrap();
+
+    let positions = world.get::<Comp<Position>>();
+    assert_eq!(positions.get(a), Some(&Position(1.0)));
+    assert_eq!(positions.get(b), Some(&Position(0.0)));
+    assert_eq!(positions.get(c), Some(&Position(0.0)));
+    drop(positions);
+
+    let (speeds, enemies) = world.get::<(Comp<Speed>, With<Enemy>)>();
+    let mut joined = vec![];
+    (&speeds, &enemies)
+        .join()
+        .for_each_with_id(|entity, (speed, ())| joined.push((entity, speed.0)));
+    assert_eq!(joined, [(d, 3.0)]);
+    assert_eq!((&speeds,).join().count(), 3);
+}
+
+#[test]
+fn join_after_removal() {
+    let mut world = World::default();
+
+    let entities: Vec<_> = (0..4)
+        .map(|n| {
+            world
+                .spawn()
+                .insert(Position(n as f32))
+                .insert(Speed(1.0))
+                .id()
+        })
+        .collect();
+
+    // Removing entities from the middle of a storage moves others around.
+    world.get::<CompMut<Speed>>().remove(entities[1]);
+    world.entity(entities[3]).despawn().unwrap();
+
+    let (positions, speeds) = world.get::<(Comp<Position>, Comp<Speed>)>();
+    let mut joined = vec![];
+    (&positions, &speeds)
+        .join()
+        .for_each_with_id(|entity, (position, _)| joined.push((entity, position.0)));
+    joined.sort_by(|a, b| a.1.total_cmp(&b.1));
+    assert_eq!(joined, [(entities[0], 0.0), (entities[2], 2.0)]);
+
+    assert_eq!(speeds.get(entities[2]), Some(&Speed(1.0)));
+}
+
+#[test]
+fn disabled_entities() {
+    let mut world = World::default();
+
+    let a = world.spawn().insert(Position(0.0)).insert(Speed(1.0)).id();
+    let b = world
+        .spawn()
+        .insert(Position(0.0))
+        .insert(Speed(1.0))
+        .disable()
+        .id();
+
+    assert!(world.entity_ref(b).is_disabled());
+    assert!(world.entity_ref(b).contains::<Speed>());
+
+    let movement = |mut positions: CompMut<Position>, speeds: Comp<Speed>| {
+        (&mut positions, &speeds)
+            .join()
+            .for_each(|(position, speed)| position.0 += speed.0);
+    };
+
+    world.run(movement).unwrap();
+    assert_eq!(world.get::<Comp<Position>>().get(a), Some(&Position(1.0)));
+    assert_eq!(world.get::<Comp<Position>>().get(b), Some(&Position(0.0)));
+
+    let entities = world.get::<Entities>();
+    assert_eq!(entities.iter().collect::<Vec<_>>(), [a]);
+    assert_eq!(entities.iter_with_disabled().count(), 2);
+
+    let (positions, disabled) = world.get::<(Comp<Position>, With<Disabled>)>();
+    assert_eq!((&positions, &disabled).join().count(), 1);
+    assert_eq!((&positions,).join().include_disabled().count(), 2);
+    drop((positions, disabled));
+
+    world.entity(b).enable();
+    world.run(movement).unwrap();
+    assert_eq!(world.get::<Comp<Position>>().get(b), Some(&Position(1.0)));
+}
+
+#[test]
+fn iterate_storage_without_disabled() {
+    #[derive(Debug, PartialEq)]
+    struct Frozen;
+    impl Component for Frozen {}
+
+    let mut world = World::default();
+    let a = world.spawn().insert(Position(1.0)).insert(Frozen).id();
+    let b = world
+        .spawn()
+        .insert(Position(2.0))
+        .insert(Frozen)
+        .disable()
+        .id();
+
+    let mut positions = world.get::<CompMut<Position>>();
+    assert_eq!(positions.iter().collect::<Vec<_>>(), [&Position(1.0)]);
+    assert_eq!(positions.ids().collect::<Vec<_>>(), [a]);
+    assert_eq!(positions.ids_with_disabled().collect::<Vec<_>>(), [a, b]);
+    assert_eq!(positions.iter_with_disabled().count(), 2);
+    assert_eq!(positions.as_slice().len(), 2);
+
+    positions.iter_mut().for_each(|position| position.0 *= 10.0);
+    assert_eq!(positions.get(a), Some(&Position(10.0)));
+    assert_eq!(positions.get(b), Some(&Position(2.0)));
+    positions
+        .iter_mut_with_disabled()
+        .for_each(|position| position.0 += 1.0);
+    assert_eq!(positions.get(b), Some(&Position(3.0)));
+    drop(positions);
+
+    // Tags are skipped too.
+    let mut frozen = world.get::<CompMut<Frozen>>();
+    assert_eq!(frozen.iter_mut().count(), 1);
+    assert_eq!(frozen.ids().collect::<Vec<_>>(), [a]);
+}
+
+#[test]
+#[cfg(feature = "parallel")]
+fn parallel_join() {
+    let mut world = World::default();
+
+    let entities: Vec<_> = (0..1000)
+        .map(|n| {
+            let mut entity = world.spawn().insert(Position(n as f32));
+            if n % 2 == 0 {
+                entity = entity.insert(Speed(2.0));
+            }
+            if n % 10 == 0 {
+                entity = entity.insert(Frozen);
+            }
+            entity.id()
+        })
+        .collect();
+    world.entity(entities[2]).disable();
+
+    world
+        .run(
+            |mut positions: CompMut<Position>, speeds: Comp<Speed>, frozen: Without<Frozen>| {
+                positions
+                    .par_join((&speeds, &frozen))
+                    .min_batch_size(16)
+                    .for_each(|position, (speed, ())| position.0 += speed.0);
+            },
+        )
+        .unwrap();
+
+    let positions = world.get::<Comp<Position>>();
+    for (n, &entity) in entities.iter().enumerate() {
+        let moved = n % 2 == 0 && n % 10 != 0 && n != 2;
+        let expected = n as f32 + if moved { 2.0 } else { 0.0 };
+        assert_eq!(positions.get(entity), Some(&Position(expected)));
+    }
+    drop(positions);
+
+    let mut speeds = world.get::<CompMut<Speed>>();
+    speeds
+        .par_iter_mut()
+        .with_min_len(64)
+        .for_each(|speed| speed.0 *= 2.0);
+    assert_eq!(speeds.par_iter().map(|speed| speed.0).sum::<f32>(), 2000.0);
+}
+
+#[test]
+#[cfg(feature = "parallel")]
+fn parallel_join_include_disabled() {
+    let mut world = World::default();
+
+    let a = world.spawn().insert(Position(0.0)).insert(Speed(1.0)).id();
+    let b = world
+        .spawn()
+        .insert(Position(0.0))
+        .insert(Speed(1.0))
+        .disable()
+        .id();
+
+    let (mut positions, speeds) = world.get::<(CompMut<Position>, Comp<Speed>)>();
+    positions
+        .par_join((&speeds,))
+        .for_each(|position, (speed,)| position.0 += speed.0);
+    assert_eq!(positions.get(a), Some(&Position(1.0)));
+    assert_eq!(positions.get(b), Some(&Position(0.0)));
+
+    positions
+        .par_join((&speeds,))
+        .include_disabled()
+        .for_each(|position, (speed,)| position.0 += speed.0);
+    assert_eq!(positions.get(a), Some(&Position(2.0)));
+    assert_eq!(positions.get(b), Some(&Position(1.0)));
+}
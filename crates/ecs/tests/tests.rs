@@ -1,5 +1,8 @@
 #![allow(clippy::disallowed_names)]
 
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+
 use ecs::prelude::*;
 
 #[derive(Debug, PartialEq, Eq)]
@@ -62,6 +65,24 @@ fn remove_both() {
     assert!(world.get::<Comp<Foo>>().get(b).is_none());
 }
 
+#[test]
+fn remove_swaps_last() {
+    let mut world = World::default();
+
+    // `a` has no component, so entity indices and dense positions differ.
+    let _a = world.spawn().id();
+    let b = world.spawn().insert(Foo(10)).id();
+    let c = world.spawn().insert(Foo(20)).id();
+    let d = world.spawn().insert(Foo(30)).id();
+
+    let b_foo = world.get::<CompMut<Foo>>().remove(b);
+    assert_eq!(b_foo, Some(Foo(10)));
+
+    assert!(world.get::<Comp<Foo>>().get(b).is_none());
+    assert_eq!(world.get::<Comp<Foo>>().get(c), Some(&Foo(20)));
+    assert_eq!(world.get::<Comp<Foo>>().get(d), Some(&Foo(30)));
+}
+
 #[test]
 fn remove_twice() {
     let mut world = World::default();
@@ -175,7 +196,7 @@ fn remove_entity() {
     let entity = world.spawn().insert(Foo(10));
     let a = entity.id();
 
-    entity.despawn();
+    entity.despawn().unwrap();
     world.entity(a);
 }
 
@@ -187,7 +208,7 @@ fn remove_entity_components() {
     let entity = world.spawn().insert(Foo(10));
     let a = entity.id();
 
-    entity.despawn();
+    entity.despawn().unwrap();
     world.get::<Comp<Foo>>().get(a);
 }
 
@@ -198,9 +219,901 @@ fn replace_entity() {
     let entity = world.spawn().insert(Foo(10));
     let a = entity.id();
 
-    entity.despawn();
+    entity.despawn().unwrap();
 
     let b = world.spawn().id();
     assert_eq!(a.entity(), b.entity());
     assert!(world.get::<Comp<Foo>>().get(b).is_none());
 }
+
+#[derive(Debug, PartialEq, Eq)]
+struct Parent(EntityId);
+impl Component for Parent {
+    fn map_entities(&mut self, entity_map: &EntityMap) {
+        self.0 = entity_map.map(self.0);
+    }
+}
+
+#[test]
+fn transfer_entity() {
+    let mut world = World::default();
+    let mut other = World::default();
+
+    let a = world.spawn().insert(Foo(10)).id();
+    let b = world.spawn().insert(Foo(20)).id();
+
+    let a_other = world.transfer(a, &mut other).unwrap();
+
+    assert_eq!(other.get::<Comp<Foo>>().get(a_other), Some(&Foo(10)));
+    assert_eq!(world.get::<Comp<Foo>>().get(b), Some(&Foo(20)));
+    assert_eq!(world.get::<Comp<Foo>>().iter().count(), 1);
+}
+
+#[test]
+#[should_panic(expected = "is dead")]
+fn transfer_dead_entity() {
+    let mut world = World::default();
+    let mut other = World::default();
+
+    let a = world.spawn().id();
+    world.entity(a).despawn().unwrap();
+
+    world.transfer(a, &mut other).unwrap();
+}
+
+#[test]
+fn transfer_skips_remove_hooks() {
+    let mut world = World::default();
+    let mut other = World::default();
+    world.insert_unique(0usize).unwrap();
+
+    world.hooks::<Foo>().on_remove(|_, foo, commands| {
+        let n = foo.0;
+        commands.push(move |world| *world.get::<UniqueMut<usize>>() += n);
+    });
+
+    let a = world.spawn().insert(Foo(10)).id();
+    let a_other = world.transfer(a, &mut other).unwrap();
+    world.flush();
+
+    assert_eq!(*world.get::<Unique<usize>>(), 0);
+    assert_eq!(other.get::<Comp<Foo>>().get(a_other), Some(&Foo(10)));
+}
+
+#[test]
+fn merge_worlds() {
+    let mut world = World::default();
+    let mut staging = World::default();
+
+    world.spawn().insert(Foo(0));
+
+    let parent = staging.spawn().insert(Foo(10)).id();
+    let child = staging.spawn().insert(Parent(parent)).id();
+    staging.spawn().insert(Foo(30)).despawn().unwrap();
+
+    let entity_map = world.merge(&mut staging).unwrap();
+    assert_eq!(entity_map.len(), 2);
+
+    let new_parent = entity_map.get(parent).unwrap();
+    let new_child = entity_map.get(child).unwrap();
+
+    assert_eq!(world.get::<Comp<Foo>>().get(new_parent), Some(&Foo(10)));
+    assert_eq!(
+        world.get::<Comp<Parent>>().get(new_child),
+        Some(&Parent(new_parent))
+    );
+    assert_eq!(world.get::<Comp<Foo>>().iter().count(), 2);
+    assert_eq!(staging.get::<Comp<Foo>>().iter().count(), 0);
+}
+
+#[test]
+fn reserve_entities() {
+    let mut world = World::default();
+
+    let a = world.spawn().id();
+    world.entity(a).despawn().unwrap();
+
+    let recycled = world.reserve_entity();
+    let new = world.reserve_entity();
+    assert_eq!(recycled.entity(), a.entity());
+    assert_ne!(new.entity(), recycled.entity());
+
+    world.entity(recycled).insert(Foo(10));
+    world.entity(new).insert(Foo(20));
+
+    let spawned = world.spawn().id();
+    assert_ne!(spawned.entity(), recycled.entity());
+    assert_ne!(spawned.entity(), new.entity());
+
+    assert_eq!(world.get::<Comp<Foo>>().get(recycled), Some(&Foo(10)));
+    assert_eq!(world.get::<Comp<Foo>>().get(new), Some(&Foo(20)));
+}
+
+#[test]
+fn reserve_entity_in_system() {
+    let mut world = World::default();
+
+    let (a, b) = world
+        .run(|entities: Entities| -> Result<_, ()> { Ok((entities.reserve(), entities.reserve())) })
+        .unwrap();
+    assert_ne!(a, b);
+
+    world.entity(b).insert(Parent(a));
+    assert_eq!(world.get::<Comp<Parent>>().get(b), Some(&Parent(a)));
+}
+
+#[test]
+fn read_reserved_entity() {
+    let mut world = World::default();
+    world.spawn().insert(Foo(10));
+    let despawned = world.spawn().insert(Foo(20)).id();
+    world.entity(despawned).despawn().unwrap();
+
+    // The first reservation reuses the despawned entity's index, and the
+    // second is brand new. Neither exists until the world is flushed.
+    let (recycled, new) = world
+        .run(|entities: Entities, foos: Comp<Foo>| -> Result<_, ()> {
+            let recycled = entities.reserve();
+            let new = entities.reserve();
+            assert_eq!(recycled.entity(), despawned.entity());
+            for entity in [recycled, new] {
+                assert_eq!(foos.get(entity), None);
+                assert!(!foos.contains(entity));
+            }
+            Ok((recycled, new))
+        })
+        .unwrap();
+
+    assert_eq!(world.get::<Comp<Foo>>().get(recycled), None);
+    assert_eq!(world.get::<Comp<Foo>>().get(new), None);
+
+    world.entity(recycled).insert(Foo(30));
+    world.entity(new).insert(Foo(40));
+    assert_eq!(world.get::<Comp<Foo>>().get(recycled), Some(&Foo(30)));
+    assert_eq!(world.get::<Comp<Foo>>().get(new), Some(&Foo(40)));
+}
+
+#[test]
+fn reserved_entities_are_not_alive() {
+    let mut world = World::default();
+    let despawned = world.spawn().id();
+    world.entity(despawned).despawn().unwrap();
+
+    let recycled = world.reserve_entity();
+    let new = world.reserve_entity();
+    assert_eq!(recycled.entity(), despawned.entity());
+
+    for entity in [recycled, new] {
+        let read = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            world.entity_ref(entity);
+        }));
+        assert!(read.is_err(), "reserved entity {entity:?} is alive");
+    }
+
+    world.flush();
+    world.entity_ref(recycled);
+    world.entity_ref(new);
+}
+
+#[test]
+fn component_hooks() {
+    let mut world = World::default();
+    world.insert_unique(0usize).unwrap();
+
+    world
+        .hooks::<Foo>()
+        .on_add(|_, foo, commands| {
+            let n = foo.0;
+            commands.push(move |world| *world.get::<UniqueMut<usize>>() += n);
+        })
+        .on_remove(|_, foo, commands| {
+            let n = foo.0;
+            commands.push(move |world| *world.get::<UniqueMut<usize>>() -= n);
+        });
+
+    let a = world.spawn().insert(Foo(10)).id();
+    world.spawn().insert(Foo(20));
+    assert_eq!(*world.get::<Unique<usize>>(), 30);
+
+    world
+        .run(|mut foos: CompMut<Foo>| {
+            foos.remove(a);
+        })
+        .unwrap();
+    assert_eq!(*world.get::<Unique<usize>>(), 20);
+
+    world.entity(a).insert(Foo(5)).despawn().unwrap();
+    assert_eq!(*world.get::<Unique<usize>>(), 20);
+}
+
+#[test]
+fn hook_commands() {
+    let mut world = World::default();
+
+    world.hooks::<Parent>().on_insert(|entity, _, commands| {
+        commands.insert(entity, Foo(1));
+    });
+
+    let a = world.spawn().id();
+    let b = world.spawn().insert(Parent(a)).id();
+
+    assert_eq!(world.get::<Comp<Foo>>().get(b), Some(&Foo(1)));
+}
+
+#[derive(Debug, Default, PartialEq)]
+struct Transform(f32);
+impl Component for Transform {}
+
+#[derive(Debug, Default, PartialEq)]
+struct Velocity(f32);
+impl Component for Velocity {}
+
+struct RigidBody;
+impl Component for RigidBody {
+    fn required(required: &mut RequiredComponents) {
+        required.add::<Transform>().add_with(|| Velocity(1.0));
+    }
+}
+
+#[test]
+fn required_components() {
+    let mut world = World::default();
+
+    let a = world.spawn().insert(RigidBody).id();
+    let b = world.spawn().insert(Transform(5.0)).insert(RigidBody).id();
+
+    assert_eq!(world.get::<Comp<Transform>>().get(a), Some(&Transform(0.0)));
+    assert_eq!(world.get::<Comp<Velocity>>().get(a), Some(&Velocity(1.0)));
+
+    assert_eq!(world.get::<Comp<Transform>>().get(b), Some(&Transform(5.0)));
+    assert_eq!(world.get::<Comp<Velocity>>().get(b), Some(&Velocity(1.0)));
+}
+
+#[test]
+fn unique_lifecycle() {
+    let mut world = World::default();
+
+    world.init_unique::<usize>();
+    assert_eq!(world.insert_unique(10usize), None);
+    assert_eq!(world.replace_unique(20usize), Some(0));
+    assert_eq!(*world.get_or_insert_with(|| 30usize), 20);
+
+    assert_eq!(world.remove_unique::<usize>(), Some(20));
+    assert_eq!(world.remove_unique::<usize>(), None);
+    assert_eq!(
+        world.try_get::<Unique<usize>>().err(),
+        Some(BorrowError::ResourceNotFound { type_name: "usize" })
+    );
+
+    *world.get_or_insert_with(|| 30usize) += 1;
+    assert_eq!(*world.get::<Unique<usize>>(), 31);
+}
+
+#[test]
+fn optional_uniques() {
+    let mut world = World::default();
+
+    world
+        .run(|a: Option<Unique<usize>>, b: Option<UniqueMut<u32>>| {
+            assert!(a.is_none());
+            assert!(b.is_none());
+        })
+        .unwrap();
+
+    world.insert_unique(10usize).unwrap();
+
+    world
+        .run(|a: Option<UniqueMut<usize>>| {
+            *a.unwrap() += 1;
+        })
+        .unwrap();
+    assert_eq!(*world.get::<Unique<usize>>(), 11);
+
+    let result = world.run(|_a: Option<Unique<usize>>, _b: UniqueMut<usize>| {});
+    assert!(matches!(
+        result,
+        Err(SystemError::BorrowError(BorrowError::AccessConflict(_)))
+    ));
+}
+
+fn is_send<S: System<P, O>, P, O: SystemOutput>(system: &S) -> bool {
+    system.is_send()
+}
+
+#[test]
+fn non_send_uniques() {
+    use std::rc::Rc;
+
+    let mut world = World::default();
+    world.insert_unique(1usize).unwrap();
+    world.insert_non_send(Rc::new(10usize)).unwrap();
+
+    let send_system = |a: Unique<usize>, _b: Comp<Foo>| {
+        assert_eq!(*a, 1);
+    };
+    let non_send_system = |mut a: NonSendMut<Rc<usize>>, _b: Unique<usize>| {
+        *a = Rc::new(20);
+    };
+
+    assert!(is_send(&send_system));
+    assert!(!is_send(&non_send_system));
+
+    world.run(send_system).unwrap();
+    world.run(non_send_system).unwrap();
+    assert_eq!(**world.get::<NonSend<Rc<usize>>>(), 20);
+
+    assert_eq!(*world.remove_non_send::<Rc<usize>>().unwrap(), 20);
+    assert!(world.try_get::<NonSend<Rc<usize>>>().is_err());
+}
+
+#[test]
+fn borrow_error_diagnostics() {
+    let mut world = World::default();
+    world.spawn().insert(Foo(0));
+
+    let state = SystemState::<CompMut<Foo>>::new(&mut world);
+    let _foos = state.get(&world);
+    let err = state.try_get(&world).err().unwrap();
+
+    let BorrowError::InvalidBorrow {
+        type_name,
+        requested,
+        held,
+        borrower,
+    } = &err
+    else {
+        panic!("expected invalid borrow, found {err:?}");
+    };
+    assert!(type_name.contains("Foo"));
+    assert_eq!(*requested, BorrowKind::Exclusive);
+    assert_eq!(*held, BorrowKind::Exclusive);
+
+    if cfg!(debug_assertions) {
+        assert_eq!(borrower.unwrap().file(), file!());
+    }
+
+    let message = err.to_string();
+    assert!(message.contains("Foo"));
+    assert!(message.contains("already borrowed mutably"));
+    drop(_foos);
+
+    // Borrowers are forgotten once their borrow ends.
+    let reads = SystemState::<Comp<Foo>>::new(&mut world);
+    let foos = reads.get(&world);
+    let line = line!() - 1;
+    drop(reads.get(&world));
+    let err = state.try_get(&world).err().unwrap();
+    assert_eq!(err.type_name(), std::any::type_name::<Foo>());
+    if cfg!(debug_assertions) {
+        let BorrowError::InvalidBorrow { borrower, .. } = err else {
+            panic!("expected invalid borrow, found {err:?}");
+        };
+        assert_eq!(borrower.unwrap().line(), line);
+    }
+    drop(foos);
+}
+
+#[test]
+fn entity_ref() {
+    let mut world = World::default();
+
+    let a = world.spawn().insert(Foo(10)).insert(Transform(1.0)).id();
+    let b = world.spawn().insert(RigidBody).id();
+
+    let entity = world.entity_ref(a);
+    assert_eq!(entity.get::<Foo>().as_deref(), Some(&Foo(10)));
+    assert!(entity.get::<Velocity>().is_none());
+    assert!(entity.contains::<Transform>());
+    assert!(!entity.contains::<RigidBody>());
+
+    // Read handles can be held at the same time.
+    let other = world.entity_ref(b);
+    assert!(other.contains::<Velocity>());
+    assert_eq!(entity.get::<Foo>().as_deref(), Some(&Foo(10)));
+
+    let names: Vec<_> = other.components().map(|info| info.name).collect();
+    assert_eq!(names.len(), 3);
+    assert!(names
+        .iter()
+        .all(|name| ["Transform", "Velocity", "RigidBody"]
+            .iter()
+            .any(|ty| name.ends_with(ty))));
+}
+
+#[derive(Default)]
+struct Marker<const N: usize>;
+impl<const N: usize> Component for Marker<N> {}
+
+#[test]
+fn component_signatures() {
+    let mut world = World::default();
+
+    let a = world.spawn().insert(Foo(0)).id();
+    let b = world.spawn().insert(Foo(1)).id();
+
+    // Register enough component types to need more than one word per entity.
+    macro_rules! insert_markers {
+        ($($n:literal)*) => {
+            $(world.entity(b).insert(Marker::<$n>);)*
+        };
+    }
+    insert_markers!(0 1 2 3 4 5 6 7 8 9 10 11 12 13 14 15 16 17 18 19 20 21 22 23 24 25 26 27 28 29 30 31 32 33 34 35 36 37 38 39 40 41 42 43 44 45 46 47 48 49 50 51 52 53 54 55 56 57 58 59 60 61 62 63 64);
+
+    assert_eq!(world.entity(a).components().count(), 1);
+    assert_eq!(world.entity(b).components().count(), 66);
+    assert!(world.entity(b).contains::<Marker<64>>());
+    assert!(!world.entity(a).contains::<Marker<64>>());
+
+    world.get::<CompMut<Marker<64>>>().remove(b);
+    world.entity(a).insert(Marker::<63>);
+    assert!(!world.entity(b).contains::<Marker<64>>());
+    assert!(world.entity(a).contains::<Marker<63>>());
+
+    // Despawned entities don't keep their signature when recycled.
+    world.entity(b).despawn().unwrap();
+    let c = world.spawn().id();
+    assert_eq!(c.entity(), b.entity());
+    assert_eq!(world.entity(c).components().count(), 0);
+
+    let info = world.entity_ref(a).components().last().copied().unwrap();
+    assert_eq!(info.type_id, std::any::TypeId::of::<Marker<63>>());
+}
+
+#[derive(Debug, PartialEq, Eq)]
+struct Player;
+impl Component for Player {}
+
+#[test]
+fn tag_components() {
+    let mut world = World::default();
+
+    let entities: Vec<_> = (0..200)
+        .map(|n| world.spawn().insert(Foo(n)).id())
+        .collect();
+    for &entity in entities.iter().step_by(3) {
+        world.entity(entity).insert(Player);
+    }
+
+    let mut players = world.get::<CompMut<Player>>();
+    assert_eq!(players.iter().count(), 67);
+    assert_eq!(players.insert(entities[0], Player), Some(Player));
+    assert_eq!(players.remove(entities[3]), Some(Player));
+    assert_eq!(players.remove(entities[4]), None);
+    assert_eq!(players.get(entities[6]), Some(&Player));
+    assert!(!players.contains(entities[3]));
+    drop(players);
+
+    world.entity(entities[198]).despawn().unwrap();
+
+    let (foos, players) = world.get::<(Comp<Foo>, Comp<Player>)>();
+    let mut joined = vec![];
+    (&foos, &players)
+        .join()
+        .for_each(|(foo, _)| joined.push(foo.0));
+    joined.sort();
+
+    let expected: Vec<_> = (0..200)
+        .step_by(3)
+        .filter(|&n| n != 3 && n != 198)
+        .collect();
+    assert_eq!(joined, expected);
+    assert_eq!((&players,).join().count(), 65);
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+struct GridCell(i32, i32);
+impl Component for GridCell {}
+
+impl Indexed for GridCell {
+    type Key = GridCell;
+
+    fn key(&self) -> GridCell {
+        *self
+    }
+}
+
+#[test]
+fn key_index() {
+    let mut world = World::default();
+
+    let a = world.spawn().insert(GridCell(0, 0)).id();
+    let b = world.spawn().insert(GridCell(1, 0)).id();
+
+    // The index is built from existing components when first borrowed.
+    let cells = world.get::<KeyIndex<GridCell>>();
+    assert_eq!(cells.get(&GridCell(0, 0)), Ok(Some(a)));
+    assert_eq!(cells.get(&GridCell(1, 0)), Ok(Some(b)));
+    assert!(!cells.contains_key(&GridCell(2, 0)));
+    drop(cells);
+
+    let c = world.spawn().insert(GridCell(1, 0)).id();
+    *world.get::<CompMut<GridCell>>().get_mut(a).unwrap() = GridCell(2, 0);
+
+    let cells = world.get::<KeyIndex<GridCell>>();
+    assert_eq!(cells.get(&GridCell(0, 0)), Ok(None));
+    assert_eq!(cells.get(&GridCell(2, 0)), Ok(Some(a)));
+    assert_eq!(cells.get_all(&GridCell(1, 0)), &[b, c]);
+    drop(cells);
+
+    world.entity(b).despawn().unwrap();
+    world.entity(a).remove::<GridCell>();
+    for cell in world.get::<CompMut<GridCell>>().iter_mut() {
+        cell.1 += 1;
+    }
+
+    let cells = world.get::<KeyIndex<GridCell>>();
+    assert_eq!(cells.get_all(&GridCell(1, 0)), &[]);
+    assert_eq!(cells.get_all(&GridCell(1, 1)), &[c]);
+    assert!(!cells.contains_key(&GridCell(2, 0)));
+}
+
+#[derive(Debug, PartialEq, Eq)]
+struct Name(&'static str);
+impl Component for Name {}
+
+impl Indexed for Name {
+    type Key = &'static str;
+    const UNIQUE: bool = true;
+
+    fn key(&self) -> &'static str {
+        self.0
+    }
+}
+
+#[test]
+fn unique_key_index() {
+    let mut world = World::default();
+
+    let a = world.spawn().insert(Name("a")).id();
+    let b = world.spawn().insert(Name("b")).id();
+
+    assert_eq!(world.get::<KeyIndex<Name>>().get(&"a"), Ok(Some(a)));
+
+    // Changes made through `get_mut` are indexed on the next borrow.
+    let mut names = world.get::<CompMut<Name>>();
+    names.get_mut(b).unwrap().0 = "c";
+    names.get_mut(b).unwrap().0 = "a";
+    drop(names);
+
+    let names = world.get::<KeyIndex<Name>>();
+    assert_eq!(names.get_all(&"a"), &[a, b]);
+    assert!(!names.contains_key(&"c"));
+    assert_eq!(
+        names.get(&"a"),
+        Err(DuplicateKeyError {
+            type_name: std::any::type_name::<Name>(),
+            entities: vec![a, b],
+        })
+    );
+    drop(names);
+
+    world.entity(a).remove::<Name>();
+    assert_eq!(world.get::<KeyIndex<Name>>().get(&"a"), Ok(Some(b)));
+}
+
+#[test]
+fn sorting_components() {
+    let mut world = World::default();
+
+    let entities: Vec<_> = [5, 3, 8, 1, 9, 2]
+        .into_iter()
+        .map(|n| {
+            world
+                .spawn()
+                .insert(Foo(n))
+                .insert(GridCell(n as i32, 0))
+                .id()
+        })
+        .collect();
+    world.entity(entities[1]).despawn().unwrap();
+
+    let mut foos = world.get::<CompMut<Foo>>();
+    foos.sort_by_key(|foo| foo.0);
+    let sorted: Vec<_> = foos.iter().map(|foo| foo.0).collect();
+    assert_eq!(sorted, [1, 2, 5, 8, 9]);
+
+    // Entities still map to their own components after sorting.
+    assert_eq!(foos.get(entities[4]), Some(&Foo(9)));
+    foos.remove(entities[3]);
+    foos.sort_by(|a, b| b.0.cmp(&a.0));
+    let sorted: Vec<_> = foos.iter().map(|foo| foo.0).collect();
+    assert_eq!(sorted, [9, 8, 5, 2]);
+    drop(foos);
+
+    let (foos, mut cells) = world.get::<(Comp<Foo>, CompMut<GridCell>)>();
+    cells.sort_like(&foos);
+    let order: Vec<_> = cells.iter().map(|cell| cell.0).collect();
+    assert_eq!(order, [9, 8, 5, 2, 1]);
+    assert_eq!(cells.get(entities[0]), Some(&GridCell(5, 0)));
+}
+
+fn foo_memory(world: &World) -> MemoryUsage {
+    let usage = world.memory_usage();
+    assert_eq!(usage[0].name, "entities");
+    *usage
+        .iter()
+        .find(|usage| usage.name == std::any::type_name::<Foo>())
+        .unwrap()
+}
+
+#[test]
+fn memory_management() {
+    let mut world = World::default();
+    world.reserve_entities(200);
+    world.get::<CompMut<Foo>>().reserve(200);
+
+    let entities: Vec<_> = (0..200)
+        .map(|n| world.spawn().insert(Foo(n)).insert(Player).id())
+        .collect();
+    let full = foo_memory(&world);
+    assert_eq!(full.len, 200);
+
+    // Sparse pages are freed once they're empty.
+    for &entity in &entities[64..128] {
+        world.entity(entity).despawn().unwrap();
+    }
+    let partial = foo_memory(&world);
+    assert_eq!(partial.len, 136);
+    assert!(partial.bytes < full.bytes);
+
+    let mut foos = world.get::<CompMut<Foo>>();
+    foos.clear();
+    assert_eq!(foos.iter().count(), 0);
+    drop(foos);
+    assert!(!world.entity(entities[0]).contains::<Foo>());
+    assert!(world.entity(entities[0]).contains::<Player>());
+
+    world.shrink_to_fit();
+    assert_eq!(foo_memory(&world).bytes, 0);
+
+    let removed = Arc::new(AtomicUsize::new(0));
+    let counter = removed.clone();
+    world.hooks::<Player>().on_remove(move |_, _, _| {
+        counter.fetch_add(1, Ordering::Relaxed);
+    });
+
+    world.clear().unwrap();
+    assert_eq!(removed.load(Ordering::Relaxed), 136);
+    assert_eq!(world.get::<Entities>().iter().count(), 0);
+    assert_eq!(world.memory_usage()[0].len, 0);
+
+    // Registrations survive clearing.
+    let entity = world.spawn().insert(Player).id();
+    world.entity(entity).despawn().unwrap();
+    assert_eq!(removed.load(Ordering::Relaxed), 137);
+}
+
+#[test]
+fn component_slices() {
+    let mut world = World::default();
+
+    let entities: Vec<_> = (0..10)
+        .map(|n| {
+            world
+                .spawn()
+                .insert(Foo(n))
+                .insert(GridCell(n as i32, 0))
+                .id()
+        })
+        .collect();
+    world.entity(entities[2]).despawn().unwrap();
+    world.entity(entities[7]).remove::<Foo>();
+
+    let mut foos = world.get::<CompMut<Foo>>();
+    assert_eq!(foos.as_slice().len(), 8);
+    for foo in foos.as_mut_slice() {
+        foo.0 *= 10;
+    }
+
+    let ids: Vec<_> = foos.ids().collect();
+    assert_eq!(ids.len(), 8);
+    for (id, foo) in ids.iter().zip(foos.as_slice()) {
+        assert_eq!(foos.get(*id), Some(foo));
+    }
+    assert_eq!(foos.get(entities[9]), Some(&Foo(90)));
+    drop(foos);
+
+    // Key indices pick up changes made through slices.
+    let _ = world.get::<KeyIndex<GridCell>>();
+    for cell in world.get::<CompMut<GridCell>>().as_mut_slice() {
+        cell.1 = 1;
+    }
+    let cells = world.get::<KeyIndex<GridCell>>();
+    assert_eq!(cells.get(&GridCell(3, 1)), Ok(Some(entities[3])));
+    assert!(!cells.contains_key(&GridCell(3, 0)));
+}
+
+#[test]
+fn entity_id_bits() {
+    assert_eq!(std::mem::size_of::<Option<EntityId>>(), 8);
+
+    let mut world = World::default();
+    let a = world.spawn().id();
+    world.entity(a).despawn().unwrap();
+    let b = world.spawn().id();
+
+    assert_eq!((b.entity(), b.version()), (0, 1));
+    assert_eq!(b.to_bits(), (1 << 32) | 1);
+    assert_eq!(EntityId::from_bits(b.to_bits()), Some(b));
+    assert_eq!(EntityId::from_bits(0), None);
+    assert_eq!(EntityId::from_bits(1 << 32), None);
+}
+
+#[test]
+fn generation_overflow() {
+    fn cycle(world: &mut World, times: usize) -> EntityId {
+        let mut entity = world.spawn().id();
+        for _ in 0..times {
+            world.entity(entity).despawn().unwrap();
+            entity = world.spawn().id();
+        }
+        entity
+    }
+
+    let mut world = World::default();
+    world.set_entity_generations(2, GenerationOverflow::Retire);
+    let entity = cycle(&mut world, 3);
+    assert_eq!((entity.entity(), entity.version()), (0, 3));
+    world.entity(entity).despawn().unwrap();
+    assert_eq!(world.spawn().id().entity(), 1);
+    assert_eq!(world.get::<Entities>().iter().count(), 1);
+
+    let mut world = World::default();
+    world.set_entity_generations(2, GenerationOverflow::Wrap);
+    let wrapped = std::sync::Arc::new(std::sync::Mutex::new(vec![]));
+    world.on_generation_wrap({
+        let wrapped = wrapped.clone();
+        move |entity| wrapped.lock().unwrap().push(entity)
+    });
+    let entity = cycle(&mut world, 4);
+    assert_eq!((entity.entity(), entity.version()), (0, 0));
+    assert_eq!(world.generation_wraps(), 1);
+
+    let wrapped = wrapped.lock().unwrap();
+    assert_eq!(wrapped.len(), 1);
+    assert_eq!((wrapped[0].entity(), wrapped[0].version()), (0, 3));
+}
+
+#[test]
+fn generation_overflow_error() {
+    let mut world = World::default();
+    world.set_entity_generations(1, GenerationOverflow::Error);
+    world.insert_unique(0usize).unwrap();
+    world.hooks::<Foo>().on_remove(|_, _, commands| {
+        commands.push(|world| *world.get::<UniqueMut<usize>>() += 1);
+    });
+
+    let entity = world.spawn().id();
+    world.entity(entity).despawn().unwrap();
+    let entity = world.spawn().insert(Foo(10)).id();
+
+    assert_eq!(
+        world.entity(entity).despawn(),
+        Err(GenerationOverflowError { entity })
+    );
+    assert_eq!(world.clear(), Err(GenerationOverflowError { entity }));
+    assert_eq!(world.get::<Comp<Foo>>().get(entity), Some(&Foo(10)));
+    assert_eq!(*world.get::<Unique<usize>>(), 0);
+}
+
+#[test]
+fn non_send_thread_checks() {
+    let mut world = World::default();
+    world.insert_unique(1usize);
+    std::thread::spawn(move || drop(world)).join().unwrap();
+
+    let mut world = World::default();
+    world.insert_non_send(std::rc::Rc::new(1usize));
+    let panic = std::thread::spawn(move || drop(world)).join().unwrap_err();
+    assert_eq!(
+        panic.downcast_ref::<&str>(),
+        Some(&"a world with non-send uniques was dropped on another thread")
+    );
+
+    let mut world = World::default();
+    world.insert_non_send(std::rc::Rc::new(1usize));
+    world.remove_non_send::<std::rc::Rc<usize>>();
+    let panic = std::thread::spawn(move || {
+        world.insert_non_send(2u32);
+    });
+    assert!(panic.join().is_err());
+}
+
+#[test]
+fn non_send_components() {
+    let mut world = World::default();
+    let entity = world
+        .spawn()
+        .insert(NonSendComponent::new(std::rc::Rc::new(1usize)))
+        .id();
+
+    let system = |mut values: CompMut<NonSendComponent<std::rc::Rc<usize>>>| {
+        for value in values.iter_mut() {
+            let next = ***value + 1;
+            *value = NonSendComponent::new(std::rc::Rc::new(next));
+        }
+    };
+    assert!(!is_send(&system));
+    world.run(system).unwrap();
+
+    let values = SystemState::<Comp<NonSendComponent<std::rc::Rc<usize>>>>::new(&mut world);
+    assert_eq!(
+        **values.get(&world).get(entity).unwrap().try_get().unwrap(),
+        2
+    );
+
+    let world = &world;
+    std::thread::scope(|scope| {
+        scope.spawn(|| {
+            let values = values.get(world);
+            let value = values.get(entity).unwrap();
+            assert!(value.try_get().is_none());
+        });
+    });
+}
+
+#[test]
+fn concurrent_readers() {
+    fn assert_send_sync<T: Send + Sync>() {}
+    assert_send_sync::<World>();
+
+    let mut world = World::default();
+    let entity = world.spawn().id();
+    for n in 0..100 {
+        world.spawn().insert(Foo(n));
+    }
+    world.insert_unique(7usize);
+    world.insert_non_send(std::rc::Rc::new(1usize));
+
+    let readers = SystemState::<(Comp<Foo>, Unique<usize>)>::new(&mut world);
+    let writer = SystemState::<CompMut<Foo>>::new(&mut world);
+    let non_send = SystemState::<Option<NonSend<std::rc::Rc<usize>>>>::new(&mut world);
+
+    let world = &world;
+    let barrier = std::sync::Barrier::new(4);
+    std::thread::scope(|scope| {
+        let threads: Vec<_> = (0..4)
+            .map(|_| {
+                scope.spawn(|| {
+                    let (foos, unique) = readers.get(world);
+
+                    // Every thread holds its borrows at the same time.
+                    barrier.wait();
+                    let sum: usize = foos.iter().map(|foo| foo.0).sum();
+                    barrier.wait();
+
+                    sum + *unique
+                })
+            })
+            .collect();
+
+        for thread in threads {
+            assert_eq!(thread.join().unwrap(), 4950 + 7);
+        }
+    });
+
+    // Conflicting borrows fail instead of blocking.
+    let (foos, _) = readers.get(world);
+    std::thread::scope(|scope| {
+        scope.spawn(|| {
+            let err = writer.try_get(world).err().unwrap();
+            assert!(matches!(
+                err,
+                BorrowError::InvalidBorrow {
+                    held: BorrowKind::Shared,
+                    ..
+                }
+            ));
+
+            // Non-send uniques can only be borrowed on the world's thread.
+            assert_eq!(
+                non_send.try_get(world).err(),
+                Some(BorrowError::WrongThread {
+                    type_name: "alloc::rc::Rc<usize>"
+                })
+            );
+        });
+    });
+    assert!(foos.get(entity).is_none());
+    assert_eq!(**non_send.get(world).unwrap(), 1);
+}
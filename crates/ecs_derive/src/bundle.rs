@@ -0,0 +1,50 @@
+use proc_macro2::TokenStream;
+use quote::quote;
+use syn::{DeriveInput, Index, Member};
+
+pub(crate) fn derive(input: DeriveInput) -> syn::Result<TokenStream> {
+    let fields = crate::struct_fields(&input, "Bundle")?;
+    let name = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+
+    let mut inserts = vec![];
+    let mut removes = vec![];
+    for (i, field) in fields.iter().enumerate() {
+        let member = match &field.ident {
+            Some(ident) => Member::Named(ident.clone()),
+            None => Member::Unnamed(Index::from(i)),
+        };
+        let ty = &field.ty;
+
+        if field
+            .attrs
+            .iter()
+            .any(|attr| attr.path().is_ident("bundle"))
+        {
+            inserts.push(quote!(.insert_bundle(self.#member)));
+            removes.push(quote!(.remove_bundle::<#ty>()));
+        } else {
+            inserts.push(quote!(.insert(self.#member)));
+            removes.push(quote!(.remove::<#ty>()));
+        }
+    }
+
+    Ok(quote! {
+        impl #impl_generics ::ecs::prelude::Bundle for #name #ty_generics #where_clause {
+            #[inline]
+            fn insert_into<'a>(
+                self,
+                entity: ::ecs::prelude::EntityMut<'a>,
+            ) -> ::ecs::prelude::EntityMut<'a> {
+                entity #(#inserts)*
+            }
+
+            #[inline]
+            fn remove_from<'a>(
+                entity: ::ecs::prelude::EntityMut<'a>,
+            ) -> ::ecs::prelude::EntityMut<'a> {
+                entity #(#removes)*
+            }
+        }
+    })
+}
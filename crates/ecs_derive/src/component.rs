@@ -0,0 +1,122 @@
+use proc_macro2::TokenStream;
+use quote::quote;
+use syn::{DeriveInput, Expr, LitStr, Path, Token};
+
+#[derive(Default)]
+struct Attributes {
+    storage: Option<TokenStream>,
+    on_add: Option<Expr>,
+    on_insert: Option<Expr>,
+    on_remove: Option<Expr>,
+
+    /// Required components, with their constructor if they aren't created
+    /// with `Default`.
+    required: Vec<(Path, Option<Expr>)>,
+}
+
+impl Attributes {
+    fn parse(input: &DeriveInput) -> syn::Result<Self> {
+        let mut attributes = Self::default();
+
+        for attr in &input.attrs {
+            if !attr.path().is_ident("component") {
+                continue;
+            }
+
+            attr.parse_nested_meta(|meta| {
+                if meta.path.is_ident("storage") {
+                    let storage: LitStr = meta.value()?.parse()?;
+                    attributes.storage = Some(match storage.value().as_str() {
+                        "sparse_set" => quote!(::ecs::prelude::StorageKind::SparseSet),
+                        "tags" => quote!({
+                            ::std::assert!(
+                                ::std::mem::size_of::<Self>() == 0,
+                                "only zero-sized components can be stored as tags",
+                            );
+                            ::ecs::prelude::StorageKind::Tags
+                        }),
+                        _ => {
+                            return Err(syn::Error::new_spanned(
+                                storage,
+                                "expected `\"sparse_set\"` or `\"tags\"`",
+                            ))
+                        }
+                    });
+                } else if meta.path.is_ident("on_add") {
+                    attributes.on_add = Some(meta.value()?.parse()?);
+                } else if meta.path.is_ident("on_insert") {
+                    attributes.on_insert = Some(meta.value()?.parse()?);
+                } else if meta.path.is_ident("on_remove") {
+                    attributes.on_remove = Some(meta.value()?.parse()?);
+                } else if meta.path.is_ident("require") {
+                    meta.parse_nested_meta(|meta| {
+                        let constructor = if meta.input.peek(Token![=]) {
+                            Some(meta.value()?.parse()?)
+                        } else {
+                            None
+                        };
+                        attributes.required.push((meta.path, constructor));
+                        Ok(())
+                    })?;
+                } else {
+                    return Err(meta.error("unknown component attribute"));
+                }
+                Ok(())
+            })?;
+        }
+
+        Ok(attributes)
+    }
+}
+
+pub(crate) fn derive(input: DeriveInput) -> syn::Result<TokenStream> {
+    let attributes = Attributes::parse(&input)?;
+    let name = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+
+    let storage = attributes.storage.map(|storage| {
+        quote! {
+            const STORAGE: ::ecs::prelude::StorageKind = #storage;
+        }
+    });
+
+    let required = (!attributes.required.is_empty()).then(|| {
+        let required =
+            attributes
+                .required
+                .iter()
+                .map(|(component, constructor)| match constructor {
+                    Some(constructor) => quote!(required.add_with::<#component>(#constructor);),
+                    None => quote!(required.add::<#component>();),
+                });
+        quote! {
+            fn required(required: &mut ::ecs::prelude::RequiredComponents) {
+                #(#required)*
+            }
+        }
+    });
+
+    let hooks = [
+        (quote!(on_add), &attributes.on_add),
+        (quote!(on_insert), &attributes.on_insert),
+        (quote!(on_remove), &attributes.on_remove),
+    ]
+    .into_iter()
+    .filter_map(|(method, hook)| hook.as_ref().map(|hook| quote!(hooks.#method(#hook);)))
+    .collect::<Vec<_>>();
+    let hooks = (!hooks.is_empty()).then(|| {
+        quote! {
+            fn hooks(hooks: &mut ::ecs::prelude::ComponentHooks<Self>) {
+                #(#hooks)*
+            }
+        }
+    });
+
+    Ok(quote! {
+        impl #impl_generics ::ecs::prelude::Component for #name #ty_generics #where_clause {
+            #storage
+            #required
+            #hooks
+        }
+    })
+}
@@ -0,0 +1,61 @@
+//! Derive macros for the `ecs` crate, re-exported from its prelude.
+
+use proc_macro::TokenStream;
+use syn::{parse_macro_input, DeriveInput};
+
+mod bundle;
+mod component;
+mod system_param;
+
+/// Implement `Component`.
+///
+/// The `#[component(...)]` attribute configures the component type:
+///
+/// - `storage = "sparse_set"` or `storage = "tags"` picks how the components
+///   are stored. Only zero-sized components can be stored as tags.
+/// - `on_add = hook`, `on_insert = hook` and `on_remove = hook` register
+///   lifecycle hooks, taking `(EntityId, &Self, &mut Commands)`.
+/// - `require(Transform, Velocity = || Velocity(1.0))` declares required
+///   components, created with `Default` unless a constructor is given.
+#[proc_macro_derive(Component, attributes(component))]
+pub fn derive_component(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    component::derive(input)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+/// Implement `Bundle` for a struct whose fields are components.
+///
+/// Fields marked `#[bundle]` are bundles themselves, and are flattened.
+#[proc_macro_derive(Bundle, attributes(bundle))]
+pub fn derive_bundle(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    bundle::derive(input)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+/// Implement `Query` for a struct whose fields are queries, so that they can
+/// be borrowed together as a single system parameter.
+///
+/// The struct must have exactly one lifetime parameter, which is the lifetime
+/// of the borrow from the world.
+#[proc_macro_derive(SystemParam)]
+pub fn derive_system_param(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    system_param::derive(input)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+/// The fields of a struct, or an error for enums and unions.
+fn struct_fields<'a>(input: &'a DeriveInput, derive: &str) -> syn::Result<&'a syn::Fields> {
+    match &input.data {
+        syn::Data::Struct(data) => Ok(&data.fields),
+        _ => Err(syn::Error::new_spanned(
+            &input.ident,
+            format!("`{derive}` can only be derived for structs"),
+        )),
+    }
+}
@@ -0,0 +1,108 @@
+use proc_macro2::TokenStream;
+use quote::quote;
+use syn::{DeriveInput, GenericParam, Index, Lifetime, Member};
+
+pub(crate) fn derive(input: DeriveInput) -> syn::Result<TokenStream> {
+    let fields = crate::struct_fields(&input, "SystemParam")?;
+    let name = &input.ident;
+
+    let lifetimes = input.generics.lifetimes().count();
+    if lifetimes != 1 {
+        return Err(syn::Error::new_spanned(
+            &input.generics,
+            "`SystemParam` structs need exactly one lifetime parameter",
+        ));
+    }
+
+    // The struct borrowed for another lifetime, as the query's item.
+    let world = Lifetime::new("'__world", proc_macro2::Span::call_site());
+    let item_generics = input.generics.params.iter().map(|param| match param {
+        GenericParam::Lifetime(_) => quote!(#world),
+        GenericParam::Type(param) => {
+            let ident = &param.ident;
+            quote!(#ident)
+        }
+        GenericParam::Const(param) => {
+            let ident = &param.ident;
+            quote!(#ident)
+        }
+    });
+
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+
+    let members: Vec<_> = fields
+        .iter()
+        .enumerate()
+        .map(|(i, field)| match &field.ident {
+            Some(ident) => Member::Named(ident.clone()),
+            None => Member::Unnamed(Index::from(i)),
+        })
+        .collect();
+    let types: Vec<_> = fields.iter().map(|field| &field.ty).collect();
+
+    // Indices are nested in pairs, since tuples are only `Clone` up to a
+    // limited length.
+    let index_type = types.iter().rev().fold(
+        quote!(()),
+        |rest, ty| quote!((<#ty as ::ecs::prelude::Query>::Index, #rest)),
+    );
+    let lookup = types.iter().rev().fold(
+        quote!(()),
+        |rest, ty| quote!((<#ty as ::ecs::prelude::Query>::lookup(world), #rest)),
+    );
+    let index_pattern = members.iter().rev().fold(quote!(()), |rest, member| {
+        let index = index_name(member);
+        quote!((#index, #rest))
+    });
+    let borrows = members.iter().zip(&types).map(|(member, ty)| {
+        let index = index_name(member);
+        quote!(#member: <#ty as ::ecs::prelude::Query>::borrow(world, #index)?)
+    });
+
+    Ok(quote! {
+        impl #impl_generics ::ecs::prelude::Query for #name #ty_generics #where_clause {
+            type Index = #index_type;
+            type Item<#world> = #name<#(#item_generics),*>;
+
+            #[inline]
+            #[allow(unused_variables)]
+            fn lookup(world: &mut ::ecs::prelude::World) -> Self::Index {
+                #lookup
+            }
+
+            #[inline]
+            #[cfg_attr(debug_assertions, track_caller)]
+            #[allow(unused_variables)]
+            fn borrow<#world>(
+                world: &#world ::ecs::prelude::World,
+                index: Self::Index,
+            ) -> ::ecs::prelude::BorrowResult<Self::Item<#world>> {
+                let #index_pattern = index;
+                ::std::result::Result::Ok(#name {
+                    #(#borrows,)*
+                })
+            }
+
+            #[inline]
+            #[allow(unused_variables)]
+            fn access(
+                access: &mut ::ecs::prelude::Access,
+            ) -> ::std::result::Result<(), ::ecs::prelude::AccessConflict> {
+                #(<#types as ::ecs::prelude::Query>::access(access)?;)*
+                ::std::result::Result::Ok(())
+            }
+
+            #[inline]
+            fn is_send() -> bool {
+                true #(&& <#types as ::ecs::prelude::Query>::is_send())*
+            }
+        }
+    })
+}
+
+fn index_name(member: &Member) -> syn::Ident {
+    match member {
+        Member::Named(ident) => quote::format_ident!("__{}_index", ident),
+        Member::Unnamed(index) => quote::format_ident!("__{}_index", index.index),
+    }
+}
@@ -0,0 +1,288 @@
+use std::any::TypeId;
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::fmt::Debug;
+
+use ecs::prelude::*;
+
+use crate::plugin::{Plugin, PluginDependencies, PluginGroup, PluginGroupBuilder};
+
+/// The schedules of an [`App`], in the order they run.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ScheduleLabel {
+    /// Runs once, before the first frame.
+    Startup,
+    /// Runs at the start of every frame.
+    PreUpdate,
+    /// Runs every frame, for most game logic.
+    Update,
+    /// Runs at the end of every frame.
+    PostUpdate,
+    /// Runs once, when the app exits.
+    Shutdown,
+}
+
+impl ScheduleLabel {
+    /// The schedules that run every frame.
+    pub const FRAME: [ScheduleLabel; 3] = [
+        ScheduleLabel::PreUpdate,
+        ScheduleLabel::Update,
+        ScheduleLabel::PostUpdate,
+    ];
+}
+
+/// A unique that systems use to stop [`App::run`] after the current frame.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct AppExit {
+    requested: bool,
+}
+
+impl AppExit {
+    /// Exit once the current frame is over.
+    #[inline]
+    pub fn request(&mut self) {
+        self.requested = true;
+    }
+
+    #[inline]
+    pub fn is_requested(&self) -> bool {
+        self.requested
+    }
+}
+
+/// A world and the schedules that run on it.
+pub struct App {
+    world: World,
+    schedules: [Schedule; 5],
+    started: bool,
+    shut_down: bool,
+
+    /// The plugins that have been built.
+    plugins: HashSet<TypeId>,
+
+    /// Plugins that were default-constructed as dependencies of other
+    /// plugins, and the plugin that depended on them.
+    implicit: HashMap<TypeId, &'static str>,
+
+    /// The plugins being built, to detect dependency cycles.
+    building: Vec<(TypeId, &'static str)>,
+
+    /// The rest of the group being added, which dependencies are taken from
+    /// before falling back to their constructors.
+    pending: VecDeque<Box<dyn Plugin>>,
+}
+
+impl Default for App {
+    fn default() -> Self {
+        let mut world = World::default();
+        world.insert_unique(AppExit::default());
+
+        Self {
+            world,
+            schedules: Default::default(),
+            started: false,
+            shut_down: false,
+            plugins: HashSet::new(),
+            implicit: HashMap::new(),
+            building: vec![],
+            pending: VecDeque::new(),
+        }
+    }
+}
+
+impl App {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    #[inline]
+    pub fn world(&self) -> &World {
+        &self.world
+    }
+
+    #[inline]
+    pub fn world_mut(&mut self) -> &mut World {
+        &mut self.world
+    }
+
+    #[inline]
+    pub fn schedule(&self, label: ScheduleLabel) -> &Schedule {
+        &self.schedules[label as usize]
+    }
+
+    #[inline]
+    pub fn schedule_mut(&mut self, label: ScheduleLabel) -> &mut Schedule {
+        &mut self.schedules[label as usize]
+    }
+
+    /// Add a system to the end of a schedule.
+    ///
+    /// Panics if the system's parameters conflict with each other.
+    pub fn add_system<S, Params, Output>(&mut self, label: ScheduleLabel, system: S) -> SystemId
+    where
+        S: System<Params, Output> + 'static,
+        Params: 'static,
+        Output: SystemOutput + 'static,
+        Output::Error: Debug + 'static,
+    {
+        self.schedule_mut(label).add_system(system)
+    }
+
+    /// Add a system to the end of a schedule with the given configuration.
+    ///
+    /// Panics if the system's parameters conflict with each other, or the
+    /// system depends on a system that isn't in the schedule.
+    pub fn add_system_with<S, Params, Output>(
+        &mut self,
+        label: ScheduleLabel,
+        system: S,
+        config: SystemConfig,
+    ) -> SystemId
+    where
+        S: System<Params, Output> + 'static,
+        Params: 'static,
+        Output: SystemOutput + 'static,
+        Output::Error: Debug + 'static,
+    {
+        self.schedule_mut(label).add_system_with(system, config)
+    }
+
+    /// Build a plugin, after any of its dependencies that haven't been added
+    /// yet.
+    ///
+    /// Panics if the plugin is unique and was already added, its
+    /// dependencies form a cycle, or it was already constructed as a
+    /// dependency of another plugin, in which case it has to be added before
+    /// that plugin.
+    pub fn add_plugin(&mut self, plugin: impl Plugin) -> &mut Self {
+        self.add_boxed_plugin(Box::new(plugin), None);
+        self
+    }
+
+    /// Build each plugin of a group, in order.
+    ///
+    /// Plugins that the group's plugins depend on are taken from the group,
+    /// even if they come later in it.
+    ///
+    /// Panics like [`App::add_plugin`].
+    pub fn add_plugins(&mut self, group: impl PluginGroup) -> &mut Self {
+        let mut builder = PluginGroupBuilder::default();
+        group.build(&mut builder);
+
+        let outer = std::mem::replace(&mut self.pending, builder.into_inner().into());
+        while let Some(plugin) = self.pending.pop_front() {
+            self.add_boxed_plugin(plugin, None);
+        }
+        self.pending = outer;
+        self
+    }
+
+    /// Whether a plugin of type `P` has been added.
+    #[inline]
+    pub fn has_plugin<P: Plugin>(&self) -> bool {
+        self.plugins.contains(&TypeId::of::<P>())
+    }
+
+    fn add_boxed_plugin(&mut self, plugin: Box<dyn Plugin>, dependent: Option<&'static str>) {
+        let id = (*plugin).type_id();
+        let name = plugin.name();
+
+        if let Some(idx) = self.building.iter().position(|&(other, _)| other == id) {
+            let cycle: Vec<_> = self.building[idx..].iter().map(|&(_, name)| name).collect();
+            panic!("plugin dependency cycle: {} -> {name}", cycle.join(" -> "));
+        }
+        if let Some(dependent) = self.implicit.get(&id) {
+            panic!(
+                "plugin {name} was already added as a dependency of {dependent}; \
+                 add it before {dependent}"
+            );
+        }
+        if plugin.is_unique() && self.plugins.contains(&id) {
+            panic!("plugin {name} was already added");
+        }
+
+        self.building.push((id, name));
+
+        let mut dependencies = PluginDependencies::default();
+        plugin.dependencies(&mut dependencies);
+        for (dependency, constructor) in dependencies.into_inner() {
+            if self.plugins.contains(&dependency) {
+                continue;
+            }
+
+            let pending = self
+                .pending
+                .iter()
+                .position(|plugin| (**plugin).type_id() == dependency);
+            match pending.and_then(|idx| self.pending.remove(idx)) {
+                Some(plugin) => self.add_boxed_plugin(plugin, None),
+                None => self.add_boxed_plugin(constructor(), Some(name)),
+            }
+        }
+
+        plugin.build(self);
+
+        self.building.pop();
+        self.plugins.insert(id);
+        if let Some(dependent) = dependent {
+            self.implicit.insert(id, dependent);
+        }
+    }
+
+    /// Whether a system has requested the app to exit, through [`AppExit`].
+    pub fn exit_requested(&mut self) -> bool {
+        self.world
+            .try_get::<Unique<AppExit>>()
+            .is_ok_and(|exit| exit.is_requested())
+    }
+
+    /// Run one frame, running the startup schedule first if it hasn't
+    /// succeeded yet.
+    ///
+    /// Returns an error if a system fails with [`ErrorPolicy::Abort`], in
+    /// which case the rest of the frame is skipped. If that happens during
+    /// startup, the next update resumes the startup schedule from the system
+    /// that failed (see [`Schedule::resume`]).
+    pub fn update(&mut self) -> Result<(), SystemFailure> {
+        if !self.started {
+            self.schedules[ScheduleLabel::Startup as usize].resume(&mut self.world)?;
+            self.started = true;
+        }
+
+        for label in ScheduleLabel::FRAME {
+            self.run_schedule(label)?;
+        }
+        Ok(())
+    }
+
+    /// Run frames until a system requests the app to exit, then run the
+    /// shutdown schedule.
+    ///
+    /// The shutdown schedule also runs if a frame fails, and the frame's
+    /// failure is returned.
+    pub fn run(&mut self) -> Result<(), SystemFailure> {
+        let result = loop {
+            if let Err(failure) = self.update() {
+                break Err(failure);
+            }
+            if self.exit_requested() {
+                break Ok(());
+            }
+        };
+
+        let shutdown = self.shutdown();
+        result.and(shutdown)
+    }
+
+    /// Run the shutdown schedule, if it hasn't run already.
+    pub fn shutdown(&mut self) -> Result<(), SystemFailure> {
+        if std::mem::replace(&mut self.shut_down, true) {
+            return Ok(());
+        }
+        self.run_schedule(ScheduleLabel::Shutdown)
+    }
+
+    fn run_schedule(&mut self, label: ScheduleLabel) -> Result<(), SystemFailure> {
+        self.schedules[label as usize].run(&mut self.world)?;
+        Ok(())
+    }
+}
@@ -0,0 +1,8 @@
+pub mod app;
+pub mod plugin;
+
+pub mod prelude {
+    pub use crate::app::*;
+    pub use crate::plugin::*;
+    pub use ecs::prelude::*;
+}
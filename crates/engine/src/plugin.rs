@@ -0,0 +1,110 @@
+use std::any::{Any, TypeId};
+
+use crate::app::App;
+
+/// A packaged set of uniques and systems to add to an [`App`].
+pub trait Plugin: Any {
+    /// Set up the app, e.g. by inserting uniques and adding systems.
+    fn build(&self, app: &mut App);
+
+    /// Declare plugins that have to be built before this one.
+    ///
+    /// These are added by [`App::add_plugin`] if the app doesn't already
+    /// have them.
+    #[inline]
+    fn dependencies(&self, _dependencies: &mut PluginDependencies) {}
+
+    /// Whether adding this plugin to an app twice is a mistake.
+    #[inline]
+    fn is_unique(&self) -> bool {
+        true
+    }
+
+    #[inline]
+    fn name(&self) -> &'static str {
+        std::any::type_name::<Self>()
+    }
+}
+
+type PluginConstructor = Box<dyn FnOnce() -> Box<dyn Plugin>>;
+
+/// The plugins a plugin depends on.
+#[derive(Default)]
+pub struct PluginDependencies(Vec<(TypeId, PluginConstructor)>);
+
+impl PluginDependencies {
+    /// Depend on a plugin, using its default value if it's missing.
+    pub fn add<P: Plugin + Default>(&mut self) -> &mut Self {
+        self.add_with(P::default)
+    }
+
+    /// Depend on a plugin, using `constructor` to create it if it's missing.
+    pub fn add_with<P: Plugin>(&mut self, constructor: fn() -> P) -> &mut Self {
+        self.0
+            .push((TypeId::of::<P>(), Box::new(move || Box::new(constructor()))));
+        self
+    }
+
+    pub(crate) fn into_inner(self) -> Vec<(TypeId, PluginConstructor)> {
+        self.0
+    }
+}
+
+/// Several plugins that are added together.
+///
+/// Implemented for tuples of up to 8 plugins.
+pub trait PluginGroup {
+    fn build(self, plugins: &mut PluginGroupBuilder);
+}
+
+/// The plugins of a [`PluginGroup`], in the order they will be added.
+#[derive(Default)]
+pub struct PluginGroupBuilder {
+    plugins: Vec<Box<dyn Plugin>>,
+}
+
+impl PluginGroupBuilder {
+    pub fn add(&mut self, plugin: impl Plugin) -> &mut Self {
+        self.plugins.push(Box::new(plugin));
+        self
+    }
+
+    /// Add another group's plugins.
+    pub fn add_group(&mut self, group: impl PluginGroup) -> &mut Self {
+        group.build(self);
+        self
+    }
+
+    /// Leave out a plugin that was added to the group.
+    pub fn disable<P: Plugin>(&mut self) -> &mut Self {
+        self.plugins
+            .retain(|plugin| (**plugin).type_id() != TypeId::of::<P>());
+        self
+    }
+
+    pub(crate) fn into_inner(self) -> Vec<Box<dyn Plugin>> {
+        self.plugins
+    }
+}
+
+macro_rules! impl_plugin_group {
+    ($($plugin:ident),*) => {
+        impl<$($plugin: Plugin),*> PluginGroup for ($($plugin,)*) {
+            #[inline]
+            #[allow(non_snake_case)]
+            fn build(self, plugins: &mut PluginGroupBuilder) {
+                let ($($plugin,)*) = self;
+                $(plugins.add($plugin);)*
+            }
+        }
+    };
+}
+
+impl_plugin_group!(P0);
+impl_plugin_group!(P0, P1);
+impl_plugin_group!(P0, P1, P2);
+impl_plugin_group!(P0, P1, P2, P3);
+impl_plugin_group!(P0, P1, P2, P3, P4);
+impl_plugin_group!(P0, P1, P2, P3, P4, P5);
+impl_plugin_group!(P0, P1, P2, P3, P4, P5, P6);
+impl_plugin_group!(P0, P1, P2, P3, P4, P5, P6, P7);
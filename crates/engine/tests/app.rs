@@ -0,0 +1,109 @@
+use engine::prelude::*;
+
+#[derive(Component)]
+struct Frame(u32);
+
+#[derive(Default)]
+struct Log(Vec<&'static str>);
+
+fn log(name: &'static str) -> impl FnMut(UniqueMut<Log>) {
+    move |mut log: UniqueMut<Log>| log.0.push(name)
+}
+
+#[test]
+fn schedules_run_in_order() {
+    let mut app = App::new();
+    app.world_mut().init_unique::<Log>();
+
+    app.add_system(ScheduleLabel::Shutdown, log("shutdown"));
+    app.add_system(ScheduleLabel::PostUpdate, log("post_update"));
+    app.add_system(ScheduleLabel::Update, log("update"));
+    app.add_system(ScheduleLabel::PreUpdate, log("pre_update"));
+    app.add_system(ScheduleLabel::Startup, log("startup"));
+
+    app.update().unwrap();
+    app.update().unwrap();
+    app.shutdown().unwrap();
+    app.shutdown().unwrap();
+
+    assert_eq!(
+        app.world_mut().get::<Unique<Log>>().0,
+        [
+            "startup",
+            "pre_update",
+            "update",
+            "post_update",
+            "pre_update",
+            "update",
+            "post_update",
+            "shutdown",
+        ]
+    );
+}
+
+#[test]
+fn run_until_exit() {
+    let mut app = App::new();
+
+    app.add_system(ScheduleLabel::Startup, |world: &mut World| {
+        world.spawn().insert(Frame(0));
+    });
+    app.add_system(
+        ScheduleLabel::Update,
+        |mut frames: CompMut<Frame>, mut exit: UniqueMut<AppExit>| {
+            for frame in frames.iter_mut() {
+                frame.0 += 1;
+                if frame.0 == 10 {
+                    exit.request();
+                }
+            }
+        },
+    );
+
+    app.run().unwrap();
+    assert!(app.exit_requested());
+    assert_eq!(app.world_mut().get::<Comp<Frame>>().as_slice()[0].0, 10);
+}
+
+#[test]
+fn run_stops_on_failure() {
+    let mut app = App::new();
+    app.world_mut().init_unique::<Log>();
+
+    app.add_system(ScheduleLabel::Update, || Err::<(), _>("failed"));
+    app.add_system(ScheduleLabel::PostUpdate, log("post_update"));
+    app.add_system(ScheduleLabel::Shutdown, log("shutdown"));
+
+    let failure = app.run().unwrap_err();
+    assert!(matches!(failure.error, SystemError::ExecutionError(_)));
+    assert_eq!(app.world_mut().get::<Unique<Log>>().0, ["shutdown"]);
+}
+
+#[test]
+fn startup_resumes_from_failed_system() {
+    let mut app = App::new();
+    app.world_mut().init_unique::<Log>();
+    app.world_mut().insert_unique(0usize).unwrap();
+
+    app.add_system(ScheduleLabel::Startup, log("load"));
+    app.add_system(ScheduleLabel::Startup, |mut attempts: UniqueMut<usize>| {
+        *attempts += 1;
+        if *attempts == 1 {
+            Err("not ready")
+        } else {
+            Ok(())
+        }
+    });
+    app.add_system(ScheduleLabel::Startup, log("startup"));
+    app.add_system(ScheduleLabel::Update, log("update"));
+
+    assert!(app.update().is_err());
+    app.update().unwrap();
+    app.update().unwrap();
+
+    assert_eq!(*app.world_mut().get::<Unique<usize>>(), 2);
+    assert_eq!(
+        app.world_mut().get::<Unique<Log>>().0,
+        ["load", "startup", "update", "update"]
+    );
+}
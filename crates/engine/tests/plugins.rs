@@ -0,0 +1,149 @@
+use engine::prelude::*;
+
+#[derive(Default)]
+struct Log(Vec<&'static str>);
+
+fn log(app: &mut App, name: &'static str) {
+    app.world_mut()
+        .get_or_insert_with(Log::default)
+        .0
+        .push(name);
+}
+
+fn built(app: &mut App) -> Vec<&'static str> {
+    app.world_mut().get::<Unique<Log>>().0.clone()
+}
+
+#[derive(Default)]
+struct Input;
+
+impl Plugin for Input {
+    fn build(&self, app: &mut App) {
+        log(app, "input");
+    }
+}
+
+#[derive(Default)]
+struct Physics;
+
+impl Plugin for Physics {
+    fn build(&self, app: &mut App) {
+        log(app, "physics");
+    }
+}
+
+struct Player {
+    speed: f32,
+}
+
+impl Plugin for Player {
+    fn dependencies(&self, dependencies: &mut PluginDependencies) {
+        dependencies.add::<Input>().add::<Physics>();
+    }
+
+    fn build(&self, app: &mut App) {
+        log(app, "player");
+        app.world_mut().insert_unique(self.speed);
+    }
+}
+
+#[test]
+fn plugin_dependencies() {
+    let mut app = App::new();
+
+    app.add_plugin(Physics).add_plugin(Player { speed: 2.0 });
+
+    assert!(app.has_plugin::<Input>());
+    assert!(app.has_plugin::<Player>());
+    assert_eq!(built(&mut app), ["physics", "input", "player"]);
+    assert_eq!(*app.world_mut().get::<Unique<f32>>(), 2.0);
+}
+
+#[test]
+fn dependency_added_later() {
+    let mut app = App::new();
+    app.add_plugins((Player { speed: 1.0 }, Physics, Input));
+    assert_eq!(built(&mut app), ["input", "physics", "player"]);
+}
+
+#[test]
+#[should_panic(expected = "already added as a dependency of")]
+fn dependency_added_after_dependent() {
+    App::new()
+        .add_plugin(Player { speed: 1.0 })
+        .add_plugin(Input);
+}
+
+#[test]
+#[should_panic(expected = "was already added")]
+fn duplicate_plugin() {
+    App::new().add_plugin(Input).add_plugin(Input);
+}
+
+struct Chicken;
+struct Egg;
+
+impl Plugin for Chicken {
+    fn dependencies(&self, dependencies: &mut PluginDependencies) {
+        dependencies.add_with(|| Egg);
+    }
+
+    fn build(&self, _app: &mut App) {}
+}
+
+impl Plugin for Egg {
+    fn dependencies(&self, dependencies: &mut PluginDependencies) {
+        dependencies.add_with(|| Chicken);
+    }
+
+    fn build(&self, _app: &mut App) {}
+}
+
+#[test]
+#[should_panic(expected = "plugin dependency cycle")]
+fn plugin_cycle() {
+    App::new().add_plugin(Chicken);
+}
+
+struct Spawner(&'static str);
+
+impl Plugin for Spawner {
+    fn build(&self, app: &mut App) {
+        log(app, self.0);
+    }
+
+    fn is_unique(&self) -> bool {
+        false
+    }
+}
+
+struct Defaults;
+
+impl PluginGroup for Defaults {
+    fn build(self, plugins: &mut PluginGroupBuilder) {
+        plugins
+            .add(Input)
+            .add(Spawner("a"))
+            .add_group((Physics, Spawner("b")));
+    }
+}
+
+#[test]
+fn plugin_groups() {
+    let mut app = App::new();
+
+    app.add_plugins(Defaults);
+    assert_eq!(built(&mut app), ["input", "a", "physics", "b"]);
+
+    let mut app = App::new();
+    struct WithoutPhysics;
+    impl PluginGroup for WithoutPhysics {
+        fn build(self, plugins: &mut PluginGroupBuilder) {
+            plugins.add_group(Defaults).disable::<Physics>();
+        }
+    }
+
+    app.add_plugins(WithoutPhysics);
+    assert!(!app.has_plugin::<Physics>());
+    assert_eq!(built(&mut app), ["input", "a", "b"]);
+}